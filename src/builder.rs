@@ -0,0 +1,179 @@
+//! Configuring a `PanicMonitor` before it's created.
+
+use {Clock, PanicMonitor, SystemClock};
+use std::time::Duration;
+
+/// A `PanicMonitor` with non-default settings.  Create one with [`PanicMonitor::builder`].
+///
+/// All the methods are `const fn`s, so a customised monitor can still live in a `static`:
+///
+/// ```
+/// use panic_monitor::PanicMonitor;
+///
+/// static PANIC_MONITOR: PanicMonitor = PanicMonitor::builder()
+///     .max_history(100)
+///     .capture_backtrace(true)
+///     .build();
+///
+/// fn main() {
+///     PANIC_MONITOR.init();
+/// }
+/// ```
+///
+/// [`PanicMonitor::builder`]: struct.PanicMonitor.html#method.builder
+#[derive(Clone, Debug)]
+pub struct PanicMonitorBuilder {
+    max_history: Option<usize>,
+    backtraces: Option<bool>,
+    chain: bool,
+    abort_after: Option<(usize, Duration)>,
+    clock: &'static dyn Clock,
+    #[cfg(feature = "log")]
+    log_panics: bool,
+    #[cfg(feature = "tracing")]
+    trace_panics: bool,
+    #[cfg(feature = "metrics")]
+    record_metrics: bool,
+}
+
+impl PanicMonitorBuilder {
+    /// A builder with the default settings; `build` gives the same thing as `PanicMonitor::new`.
+    pub const fn new() -> PanicMonitorBuilder {
+        PanicMonitorBuilder {
+            max_history: None,
+            backtraces: None,
+            chain: true,
+            abort_after: None,
+            clock: &SystemClock,
+            #[cfg(feature = "log")]
+            log_panics: false,
+            #[cfg(feature = "tracing")]
+            trace_panics: false,
+            #[cfg(feature = "metrics")]
+            record_metrics: false,
+        }
+    }
+
+    /// Remember at most `max` panics, throwing away the oldest.  See
+    /// [`PanicMonitor::with_capacity`].  The default is to remember everything.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero, since the monitor would throw every record away (or, in a
+    /// `static`, fails to compile).
+    ///
+    /// [`PanicMonitor::with_capacity`]: struct.PanicMonitor.html#method.with_capacity
+    pub const fn max_history(mut self, max: usize) -> PanicMonitorBuilder {
+        assert!(max > 0, "max_history: max must be at least 1");
+        self.max_history = Some(max);
+        self
+    }
+
+    /// Always (or never) capture a backtrace when a thread panics.  By default, backtraces are
+    /// captured if they're enabled by the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment
+    /// variables.  Without a backtrace, [double panics] aren't detected.
+    ///
+    /// [double panics]: struct.PanicRecord.html#method.is_double_panic
+    pub const fn capture_backtrace(mut self, capture: bool) -> PanicMonitorBuilder {
+        self.backtraces = Some(capture);
+        self
+    }
+
+    /// Whether the monitor's hook should call the hook which was installed before it (normally
+    /// std's default hook, which prints the panic message to stderr).  The default is to do so.
+    ///
+    /// Pass `false` to record panics quietly, eg. in tests which panic a lot on purpose, or if
+    /// you'd rather report panics yourself.
+    pub const fn chain_previous_hook(mut self, chain: bool) -> PanicMonitorBuilder {
+        self.chain = chain;
+        self
+    }
+
+    /// Abort the process once `count` panics have happened within `window` of each other.  The
+    /// default is never to abort.
+    ///
+    /// This turns the monitor into a crash-loop detector: if workers which keep getting restarted
+    /// keep panicking, it's probably better to fail fast than to thrash.  The panic which trips
+    /// the breaker is recorded (and passed on to the previous hook) before the process aborts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero, since the process would abort on the first panic (or, in a
+    /// `static`, fails to compile).
+    pub const fn abort_after(mut self, count: usize, window: Duration) -> PanicMonitorBuilder {
+        assert!(count > 0, "abort_after: count must be at least 1");
+        self.abort_after = Some((count, window));
+        self
+    }
+
+    /// Get the time from `clock` instead of the system clock.  See [`Clock`].
+    ///
+    /// This is for tests of time-dependent behaviour, like [`abort_after`]: with a [`MockClock`],
+    /// they can move time forward by hand instead of sleeping.
+    ///
+    /// [`abort_after`]: #method.abort_after
+    /// [`Clock`]: trait.Clock.html
+    /// [`MockClock`]: struct.MockClock.html
+    pub const fn clock(mut self, clock: &'static dyn Clock) -> PanicMonitorBuilder {
+        self.clock = clock;
+        self
+    }
+
+    /// Emit a `log` error every time a thread panics.  See [`PanicMonitor::log_panics`].
+    ///
+    /// This method is only available with the `log` feature.
+    ///
+    /// [`PanicMonitor::log_panics`]: struct.PanicMonitor.html#method.log_panics
+    #[cfg(feature = "log")]
+    pub const fn log_panics(mut self, log_panics: bool) -> PanicMonitorBuilder {
+        self.log_panics = log_panics;
+        self
+    }
+
+    /// Emit a `tracing` event every time a thread panics.  See [`PanicMonitor::trace_panics`].
+    ///
+    /// This method is only available with the `tracing` feature.
+    ///
+    /// [`PanicMonitor::trace_panics`]: struct.PanicMonitor.html#method.trace_panics
+    #[cfg(feature = "tracing")]
+    pub const fn trace_panics(mut self, trace_panics: bool) -> PanicMonitorBuilder {
+        self.trace_panics = trace_panics;
+        self
+    }
+
+    /// Report panics to the `metrics` recorder.  See [`PanicMonitor::record_metrics`].
+    ///
+    /// This method is only available with the `metrics` feature.
+    ///
+    /// [`PanicMonitor::record_metrics`]: struct.PanicMonitor.html#method.record_metrics
+    #[cfg(feature = "metrics")]
+    pub const fn record_metrics(mut self, record_metrics: bool) -> PanicMonitorBuilder {
+        self.record_metrics = record_metrics;
+        self
+    }
+
+    /// Create the `PanicMonitor`.  You must call [`init`] on it, as usual.
+    ///
+    /// [`init`]: struct.PanicMonitor.html#method.init
+    pub const fn build(self) -> PanicMonitor {
+        let mut monitor = PanicMonitor::new();
+        monitor.max_history = self.max_history;
+        monitor.backtraces = self.backtraces;
+        monitor.chain = self.chain;
+        monitor.abort_after = self.abort_after;
+        monitor.clock = self.clock;
+        #[cfg(feature = "log")]
+        { monitor.log_panics = self.log_panics; }
+        #[cfg(feature = "tracing")]
+        { monitor.trace_panics = self.trace_panics; }
+        #[cfg(feature = "metrics")]
+        { monitor.record_metrics = self.record_metrics; }
+        monitor
+    }
+}
+
+impl Default for PanicMonitorBuilder {
+    fn default() -> PanicMonitorBuilder {
+        PanicMonitorBuilder::new()
+    }
+}
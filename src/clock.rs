@@ -0,0 +1,91 @@
+//! Where the monitor gets the time from.
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use sync::{lock, Mutex};
+
+/// A source of time.  The monitor uses it to timestamp its [`PanicRecord`]s (and so to decide
+/// when [`abort_after`]'s window has passed), and [`supervise_with_clock`] uses it to wait out its
+/// backoff delays.
+///
+/// The default is [`SystemClock`].  Tests can use a [`MockClock`] instead, to control time by
+/// hand rather than by sleeping.
+///
+/// The `wait_timeout` family of methods always use the system clock, since they block on a
+/// condition variable.  So does [`PanicRecord::system_time`].
+///
+/// [`abort_after`]: struct.PanicMonitorBuilder.html#method.abort_after
+/// [`MockClock`]: struct.MockClock.html
+/// [`PanicRecord`]: struct.PanicRecord.html
+/// [`PanicRecord::system_time`]: struct.PanicRecord.html#method.system_time
+/// [`supervise_with_clock`]: supervisor/fn.supervise_with_clock.html
+/// [`SystemClock`]: struct.SystemClock.html
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> Instant;
+
+    /// Block the current thread for `dur`.  The default implementation uses [`thread::sleep`].
+    ///
+    /// [`thread::sleep`]: https://doc.rust-lang.org/std/thread/fn.sleep.html
+    fn sleep(&self, dur: Duration) {
+        thread::sleep(dur);
+    }
+}
+
+/// The real time, as given by [`Instant::now`].
+///
+/// [`Instant::now`]: https://doc.rust-lang.org/std/time/struct.Instant.html#method.now
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which only moves when it's told to.
+///
+/// Time starts at the moment the clock was created, and advances by calling [`advance`].
+/// Sleeping on a `MockClock` doesn't block; it just advances the clock.
+///
+/// [`advance`]: #method.advance
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// Create a new clock, reading the current real time.
+    pub fn new() -> MockClock {
+        MockClock { start: Instant::now(), elapsed: Mutex::new(Duration::from_secs(0)) }
+    }
+
+    /// Move the clock forward by `dur`.
+    pub fn advance(&self, dur: Duration) {
+        *lock(&self.elapsed) += dur;
+    }
+
+    /// How far the clock has moved since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *lock(&self.elapsed)
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, dur: Duration) {
+        self.advance(dur);
+    }
+}
@@ -0,0 +1,67 @@
+//! Named sets of threads.
+
+use std::sync::Arc;
+use std::thread::ThreadId;
+use sync::{lock, Mutex};
+
+/// A named set of threads, such as a pool of workers, which can be watched as a unit with
+/// [`PanicMonitor::wait_group`].
+///
+/// Clones of a group share their members: adding a thread to one adds it to all of them.  This
+/// means that a group can be handed out to several supervisor threads.  Removing a thread once its
+/// panic has been dealt with gives edge-triggered behaviour on a per-group basis.
+///
+/// [`PanicMonitor::wait_group`]: struct.PanicMonitor.html#method.wait_group
+#[derive(Clone)]
+pub struct ThreadGroup {
+    name: Arc<str>,
+    members: Arc<Mutex<Vec<ThreadId>>>,  // In the order they were added, without duplicates
+}
+
+impl ThreadGroup {
+    /// Create a new, empty group.
+    pub fn new(name: &str) -> ThreadGroup {
+        ThreadGroup { name: Arc::from(name), members: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// The name the group was created with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add a thread to the group.  Returns false if it was already a member.
+    pub fn add(&self, tid: ThreadId) -> bool {
+        let mut members = lock(&self.members);
+        if members.contains(&tid) { return false; }
+        members.push(tid);
+        true
+    }
+
+    /// Remove a thread from the group.  Returns false if it wasn't a member.
+    pub fn remove(&self, tid: ThreadId) -> bool {
+        let mut members = lock(&self.members);
+        let len = members.len();
+        members.retain(|&x| x != tid);
+        members.len() != len
+    }
+
+    /// Is the thread a member of the group?
+    pub fn contains(&self, tid: ThreadId) -> bool {
+        lock(&self.members).contains(&tid)
+    }
+
+    /// The members of the group, in the order they were added.
+    pub fn ids(&self) -> Vec<ThreadId> {
+        lock(&self.members).clone()
+    }
+
+    /// The number of threads in the group.
+    pub fn len(&self) -> usize {
+        lock(&self.members).len()
+    }
+
+    /// Is the group empty?
+    pub fn is_empty(&self) -> bool {
+        lock(&self.members).is_empty()
+    }
+}
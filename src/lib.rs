@@ -14,42 +14,51 @@ panic_monitor handles both of these issues.  [`PanicMonitor::wait`] allows you t
 of threads.  As soon as one of them panics, it returns a [`Thread`] struct (which contains the name
 and ID of the panicking thread).  When calling [`PanicMonitor::wait`], you specify the watch-list
 in terms of [`ThreadId`]s.  Since these are clonable, mulitple supervisor threads can monitor the
-same worker thread.
+same worker thread.  (You can pass a [`JoinHandle`] or a [`Thread`] instead, to save looking up
+its ID.)  If you don't know the IDs up front, an empty watch-list watches every thread.
 
 Some other differences between [`PanicMonitor::wait`] and [`JoinHandle::join`]:
 
  * You don't receive the value which was passed to [`panic`].  (This would be impossible, given
-   that such values are not required to implement [`Clone`].)
+   that such values are not required to implement [`Clone`].)  If you want to know *why* a thread
+   panicked, use [`PanicMonitor::wait_records`] and friends, which return a [`PanicRecord`]
+   containing a string rendering of the panic message.
  * You aren't notified when a thread shuts down normally.  `PanicMonitor` is for handling
-   panicking threads only.
+   panicking threads only.  (The exception is [`PanicMonitor::wait_done`], which also returns
+   when a thread spawned by the monitor finishes.)
+ * A panic which is caught by [`std::panic::catch_unwind`] is still recorded, even though the
+   thread carries on running: the panic hook runs before anyone knows whether the panic will be
+   caught.  If a thread recovers from panics this way, use [`panic_monitor::catch_unwind`]
+   instead, which stops the monitor from recording them.
 
 [`PanicMonitor::wait`]: struct.PanicMonitor.html#method.wait
+[`PanicMonitor::wait_done`]: struct.PanicMonitor.html#method.wait_done
+[`PanicMonitor::wait_records`]: struct.PanicMonitor.html#method.wait_records
+[`PanicRecord`]: struct.PanicRecord.html
 [`JoinHandle`]: https://doc.rust-lang.org/std/thread/struct.JoinHandle.html
 [`JoinHandle::join`]: https://doc.rust-lang.org/std/thread/struct.JoinHandle.html#method.join
 [`panic`]: https://doc.rust-lang.org/std/macro.panic.html
+[`std::panic::catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+[`panic_monitor::catch_unwind`]: fn.catch_unwind.html
 [`Clone`]: https://doc.rust-lang.org/std/clone/trait.Clone.html
 [`Thread`]: https://doc.rust-lang.org/std/thread/struct.Thread.html
 [`ThreadId`]: https://doc.rust-lang.org/std/thread/struct.ThreadId.html
 
 ## Usage
 
-Create a global [`PanicMonitor`] using [`lazy_static`], and initialise it from your main thread.
-Ideally you should do this before spawning any new threads.
+Create a global [`PanicMonitor`] in a `static`, and initialise it from your main thread.  Ideally
+you should do this before spawning any new threads.
 
 [`PanicMonitor`]: struct.PanicMonitor.html
-[`lazy_static`]: https://docs.rs/lazy_static/1.0.0/lazy_static/macro.lazy_static.html
 
 ```
-#[macro_use] extern crate lazy_static;
 extern crate panic_monitor;
 
 use panic_monitor::PanicMonitor;
 use std::thread;
 use std::time::Duration;
 
-lazy_static! {
-    static ref PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
-}
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
 
 fn main() {
     // Install a panic hook
@@ -60,65 +69,893 @@ fn main() {
         panic!();
     });
 
-    PANIC_MONITOR.wait(&[h.thread().id()]);
+    PANIC_MONITOR.wait([&h]);
     // ^ this will block until the thread panics
 
-    PANIC_MONITOR.wait(&[h.thread().id()]);
+    PANIC_MONITOR.wait([&h]);
     // ^ this will return immediately, since the thread is already dead
 
     h.join().unwrap_err();
 }
 ```
+
+If the monitor can't be constructed in a `const` context (for instance, because its settings
+come from a config file), a [`OnceLock`] works just as well.  Since the `OnceLock` is a `static`,
+the reference it hands out is `'static`, so [`init`] can be called on it directly:
+
+[`OnceLock`]: https://doc.rust-lang.org/std/sync/struct.OnceLock.html
+[`init`]: struct.PanicMonitor.html#method.init
+
+```
+use panic_monitor::PanicMonitor;
+use std::sync::OnceLock;
+use std::thread;
+
+static PANIC_MONITOR: OnceLock<PanicMonitor> = OnceLock::new();
+
+fn panic_monitor() -> &'static PanicMonitor {
+    PANIC_MONITOR.get_or_init(PanicMonitor::new)
+}
+
+fn main() {
+    panic_monitor().init();
+
+    let h = thread::spawn(|| panic!());
+    panic_monitor().wait(&[h.thread().id()]);
+    h.join().unwrap_err();
+}
+```
+
+If you only need one monitor, you needn't declare it at all: the crate has a global one, which
+the free functions [`init`](fn.init.html), [`wait`], and [`check`] use.  (For its other methods,
+use [`global`].)
+
+[`check`]: fn.check.html
+[`global`]: fn.global.html
+[`wait`]: fn.wait.html
+
+```
+use std::thread;
+
+fn main() {
+    panic_monitor::init();
+
+    let h = thread::spawn(|| panic!());
+    panic_monitor::wait([&h]);
+    assert_eq!(panic_monitor::check([&h]).len(), 1);
+    h.join().unwrap_err();
+}
+```
+
+## Scoped threads
+
+Threads spawned with [`thread::scope`] are watched just like any others.  `wait` returns as soon as
+a scoped thread panics, without waiting for the end of the scope, so a supervisor can run inside
+the scope alongside its workers.  A scoped thread which panicked should still be joined: if the
+scope has to join it implicitly, `scope` itself will panic.
+
+[`thread::scope`]: https://doc.rust-lang.org/std/thread/fn.scope.html
+
+```
+use panic_monitor::PanicMonitor;
+use std::sync::mpsc;
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+fn main() {
+    PANIC_MONITOR.init();
+
+    let (tx, rx) = mpsc::channel::<()>();
+    thread::scope(|s| {
+        let healthy = s.spawn(move || { let _ = rx.recv(); });
+        let broken = s.spawn(|| panic!());
+        PANIC_MONITOR.wait([broken.thread().id(), healthy.thread().id()]);
+        // ^ this returns while `healthy` is still running
+
+        drop(tx);
+        broken.join().unwrap_err();
+    });
+}
+```
+
+## Thread IDs
+
+The monitor keys its records by [`ThreadId`], and relies on the guarantee that a `ThreadId` is
+never reused, not even after its thread has finished.  So an ID in a watch list always refers to
+the same thread, and a new thread can never be mistaken for an old one which happened to panic.
+
+The flip side is that the records don't go away by themselves: a process which keeps spawning
+threads which panic keeps adding entries to the monitor.  To keep memory bounded, use
+[`PanicMonitor::with_capacity`], [`PanicMonitor::prune`], or [`PanicMonitor::forget`].  The
+monitor also remembers a [thread number] for each thread it's seen, which `with_capacity` doesn't
+bound: only `prune`, `forget`, and `reset` throw those away.  Since IDs are never reused, throwing
+a thread's records away is safe: no other thread will ever turn up under its ID.
+
+[`PanicMonitor::forget`]: struct.PanicMonitor.html#method.forget
+[`PanicMonitor::prune`]: struct.PanicMonitor.html#method.prune
+[`PanicMonitor::with_capacity`]: struct.PanicMonitor.html#method.with_capacity
+[thread number]: struct.PanicRecord.html#method.thread_number
+
+## Optional features
+
+ * `futures`: async versions of the waiting methods ([`PanicMonitor::wait_async`] and
+   [`PanicMonitor::stream`]).
+ * `crossbeam`: [`PanicMonitor::subscribe_crossbeam`], for use with crossbeam's `select!`.
+ * `serde`: `Serialize` for [`PanicRecord`].
+ * `log` and `tracing`: report panics to the respective logging framework.
+ * `metrics`: count panics with the `metrics` crate ([`PanicMonitor::record_metrics`]).
+ * `parking_lot`: use parking_lot's locks instead of std's.
+ * `require_unwind`: fail the build if `panic = "abort"` is set (see below).
+
+[`PanicMonitor::record_metrics`]: struct.PanicMonitor.html#method.record_metrics
+[`PanicMonitor::subscribe_crossbeam`]: struct.PanicMonitor.html#method.subscribe_crossbeam
+[`PanicMonitor::wait_async`]: struct.PanicMonitor.html#method.wait_async
+[`PanicMonitor::stream`]: struct.PanicMonitor.html#method.stream
+
+## `panic = "abort"`
+
+panic_monitor is only useful if panics unwind, which is the default.  If your program is built
+with `panic = "abort"`, the monitor's hook still runs, so the panic still gets recorded, logged,
+and passed to any [`on_panic`] callbacks; but the process aborts as soon as the hook returns, so
+nobody ever gets to `wait` for it.  Enable the `require_unwind` feature to turn this mistake into a
+compile error.
+
+[`on_panic`]: struct.PanicMonitor.html#method.on_panic
+
+## `no_std`
+
+panic_monitor requires std, and there's no `std` feature to turn off.  Everything it does hinges
+on [`std::panic::set_hook`], which has no `no_std` equivalent: a `#[panic_handler]` can't return,
+so there's no way for a `no_std` program to observe a panic and carry on.
+
+[`std::panic::set_hook`]: https://doc.rust-lang.org/std/panic/fn.set_hook.html
 */
 
-use std::collections::HashMap;
+#[cfg(all(feature = "require_unwind", panic = "abort"))]
+compile_error!("panic_monitor can't observe panics when built with `panic = \"abort\"`");
+
+#[cfg(feature = "crossbeam")] extern crate crossbeam_channel;
+#[cfg(feature = "futures")] extern crate futures;
+#[cfg(feature = "log")] #[macro_use] extern crate log;
+#[cfg(feature = "metrics")] extern crate metrics;
+#[cfg(feature = "parking_lot")] extern crate parking_lot;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "tracing")] extern crate tracing;
+
+mod builder;
+mod clock;
+mod group;
+pub mod supervisor;
+mod sync;
+mod watch;
+
+pub use builder::PanicMonitorBuilder;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use group::ThreadGroup;
+pub use watch::{WatchHandle, WatchList, Watchable, ALL_THREADS};
+
+#[cfg(feature = "futures")] use futures::channel::mpsc::{unbounded, UnboundedSender};
+#[cfg(feature = "futures")] use futures::Stream;
+#[cfg(feature = "serde")] use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fmt;
+use std::hash::BuildHasherDefault;
+use std::io;
+#[cfg(feature = "futures")] use std::future::Future;
 use std::panic;
-use std::sync::*;
-use std::thread::{self, Thread, ThreadId};
+use std::process;
+#[cfg(feature = "futures")] use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Once, OnceLock, Weak};
+use std::task::{Context, Poll, Waker};
+use sync::{lock, read, write, Condvar, Mutex, RwLock};
+use std::thread::{self, JoinHandle, Thread, ThreadId};
 use std::time::*;
 
-const POISON_MSG: &str = "panic_monitor: Inner lock poisoned (please submit a bug report)";
-
 /// A list of all threads which have panicked, with the ability to notify interested parties when
 /// this list is updated.
+///
+/// `PanicMonitor` is `Send` and `Sync`, so it can be put in a `static` or shared via an `Arc`.
+/// Everything it contains (including the callbacks registered with [`on_panic`]) is required to be
+/// `Send + Sync` too.
+///
+/// None of the monitor's methods fail, so there's no error type.  In particular, they don't panic
+/// if a thread panicked while holding one of the monitor's internal locks: the data behind each
+/// lock is valid after every individual update, so the monitor ignores poisoning and carries on.
+/// The one way to misuse the monitor is to forget to initialise it, which makes waiting on it
+/// hang; see [`is_initialized`].
+///
+/// [`is_initialized`]: #method.is_initialized
+/// [`on_panic`]: #method.on_panic
 pub struct PanicMonitor {
-    panicked: Mutex<HashMap<ThreadId, Thread>>,   // All threads which have historically panicked
-    cvar: Condvar,
+    panicked: RwLock<Panicked>,  // All threads which have ever panicked
+    waiters: Mutex<Waiters>,     // Blocked threads, indexed by what they're waiting for
+    waiter_count: AtomicUsize,   // ...and how many of them there are
+    panic_count: AtomicUsize,    // Total number of panics, including repeats
+    thread_panic_counts: Mutex<Map<ThreadId, usize>>,  // ...and broken down by thread
+    next_seq: AtomicU64,         // Sequence number of the next record
+    max_history: Option<usize>,  // Maximum number of records to keep
+    backtraces: Option<bool>,    // Whether to capture backtraces (`None` means "ask std")
+    chain: bool,                 // Whether to call the previously-installed hook
+    abort_after: Option<(usize, Duration)>,  // Abort after this many panics within this window
+    clock: &'static dyn Clock,               // For timestamping records
+    recent: Mutex<VecDeque<Instant>>,        // Times of the panics within the window
+    callbacks: RwLock<Vec<Callback>>,  // Run from the hook, in registration order
+    wait_callbacks: RwLock<Vec<WaitCallback>>,  // Run by waiters, when they return
+    classifier: RwLock<Option<Classifier>>,
+    wakeups: AtomicU64,        // Times a blocked waiter has been woken...
+    panic_wakeups: AtomicU64,  // ...and how many of those found what it was waiting for
+    coalesced: AtomicU64,      // Notifications which were skipped, since one was already pending
+    #[cfg(feature = "log")]
+    log_panics: bool,
+    #[cfg(feature = "tracing")]
+    trace_panics: bool,
+    #[cfg(feature = "metrics")]
+    record_metrics: bool,
+    spawned: Mutex<Vec<Spawned>>,    // Threads spawned via `spawn`, until they're pruned
+    owned: Mutex<Vec<JoinHandle<()>>>,  // Threads spawned via `spawn_owned`, until they're joined
+    hook: Mutex<Option<Installed>>,  // Set while the hook is installed
+    warned_uninitialized: AtomicBool,  // Whether `wait` has complained about the above being unset
+    thread_numbers: Mutex<Map<ThreadId, u64>>,
+    next_thread_number: AtomicU64,
+    subscribers: Mutex<Vec<(Vec<ThreadId>, Arc<Subscriber>)>>,
+    wakers: Mutex<Vec<(Vec<ThreadId>, WakerEntry)>>,
+}
+
+// The whole API relies on this: a regression in one of the fields' types should fail the build
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PanicMonitor>();
+    assert_send_sync::<PanicRecord>();
+    assert_send_sync::<Payload>();
+    assert_send_sync::<CancelToken>();
+    assert_send_sync::<ThreadGroup>();
+    assert_send_sync::<WatchHandle>();
+    assert_send_sync::<Cursor>();
+};
+
+/// Information about a thread which panicked.
+///
+/// A record is created in full by the panic hook, before anyone can see it, and never changes
+/// afterwards.  So the thread, message, location, and timestamp always describe the same panic.
+/// If you need more than the [`Thread`], use one of the `_records` methods (eg.
+/// [`PanicMonitor::wait_records`]) rather than looking the details up after the fact: by then,
+/// the thread may have panicked again, or its records may have been evicted.
+///
+/// [`Thread`]: https://doc.rust-lang.org/std/thread/struct.Thread.html
+/// [`PanicMonitor::wait_records`]: struct.PanicMonitor.html#method.wait_records
+#[derive(Clone, Debug)]
+pub struct PanicRecord {
+    thread: Thread,
+    thread_number: u64,
+    payload: Payload,
+    location: Option<(String, u32, u32)>,
+    backtrace: Option<String>,
+    context: Option<String>,
+    class: Option<u32>,
+    double_panic: bool,
+    instant: Instant,
+    system_time: SystemTime,
+    seq: u64,  // Assigned under the write lock, so records are numbered in the order they're stored
+}
+
+impl PanicRecord {
+    /// The thread which panicked.
+    pub fn thread(&self) -> &Thread {
+        &self.thread
+    }
+
+    /// A number which identifies the thread which panicked.
+    ///
+    /// Unlike `ThreadId` (whose `Debug` output isn't guaranteed to be stable), this can be logged
+    /// or serialized: the monitor numbers threads in the order it first sees them, starting from
+    /// zero.  A thread is first seen when it's spawned with [`PanicMonitor::spawn`], or otherwise
+    /// when it first panics.  All the records of a given thread have the same number, and numbers
+    /// are never reused.  See also [`PanicMonitor::thread_number`].
+    ///
+    /// [`PanicMonitor::spawn`]: struct.PanicMonitor.html#method.spawn
+    /// [`PanicMonitor::thread_number`]: struct.PanicMonitor.html#method.thread_number
+    pub fn thread_number(&self) -> u64 {
+        self.thread_number
+    }
+
+    /// The value which was passed to [`panic`], rendered as a string.
+    ///
+    /// Only `&str` and `String` payloads can be rendered; the message of a panic with any other
+    /// kind of payload is `"Box<dyn Any>"`.  Use [`payload`] to tell these apart from panics
+    /// whose message really was `"Box<dyn Any>"`.
+    ///
+    /// [`panic`]: https://doc.rust-lang.org/std/macro.panic.html
+    /// [`payload`]: #method.payload
+    pub fn message(&self) -> &str {
+        match self.payload {
+            Payload::Str(ref s) => s,
+            Payload::Other => "Box<dyn Any>",
+        }
+    }
+
+    /// What kind of value was passed to [`panic`].  See [`Payload`].
+    ///
+    /// [`panic`]: https://doc.rust-lang.org/std/macro.panic.html
+    /// [`Payload`]: enum.Payload.html
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    /// The location in the source code at which the thread panicked, as a `(file, line, column)`
+    /// triple.  This is almost always available.
+    pub fn location(&self) -> Option<(&str, u32, u32)> {
+        self.location.as_ref().map(|&(ref file, line, col)| (file.as_str(), line, col))
+    }
+
+    /// A backtrace of the panicking thread, captured from inside the panic hook.
+    ///
+    /// Backtraces are only captured if they've been enabled with the `RUST_BACKTRACE` or
+    /// `RUST_LIB_BACKTRACE` environment variables (see [`Backtrace::capture`]); otherwise this
+    /// returns `None`.
+    ///
+    /// [`Backtrace::capture`]: https://doc.rust-lang.org/std/backtrace/struct.Backtrace.html#method.capture
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+
+    /// The context which the thread had set with [`set_context`] when it panicked, if any.
+    ///
+    /// [`set_context`]: fn.set_context.html
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    /// The code which the monitor's [payload classifier] gave to the panic's payload, if any.
+    ///
+    /// [payload classifier]: struct.PanicMonitor.html#method.classify_payloads
+    pub fn class(&self) -> Option<u32> {
+        self.class
+    }
+
+    /// Did the thread panic while it was already unwinding from an earlier panic (eg. in a
+    /// `Drop` impl)?
+    ///
+    /// A double panic aborts the process straight after the panic hook has run, so nobody gets
+    /// the chance to wait for this record.  But it's still made, and logged (with the `log` or
+    /// `tracing` features) or passed to the previously-installed hook before the process goes
+    /// down, which can help with the post-mortem.
+    ///
+    /// This is best-effort, since std doesn't say whether a thread is already unwinding when it
+    /// panics.  A panic counts as a double panic if the thread has panicked before and the new
+    /// panic comes from a `Drop` impl, which the monitor finds out from the record's
+    /// [`backtrace`].  So double panics are only detected when backtraces are captured (see
+    /// [`PanicMonitorBuilder::capture_backtrace`]), and only as reliably as the backtrace is:
+    /// without debug info, they may go unnoticed.
+    ///
+    /// std doesn't say when a panic has been caught, either.  If a thread recovers from a panic
+    /// with [`std::panic::catch_unwind`] and later panics in a `Drop` impl, that's reported as a
+    /// double panic too, unless the thread has called [`set_context`] or [`clear_context`] in
+    /// between: that's when the monitor notices that the first panic is over.  Threads which
+    /// recover with this crate's [`catch_unwind`] aren't affected.
+    ///
+    /// [`backtrace`]: #method.backtrace
+    /// [`catch_unwind`]: fn.catch_unwind.html
+    /// [`clear_context`]: fn.clear_context.html
+    /// [`PanicMonitorBuilder::capture_backtrace`]: struct.PanicMonitorBuilder.html#method.capture_backtrace
+    /// [`set_context`]: fn.set_context.html
+    /// [`std::panic::catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+    pub fn is_double_panic(&self) -> bool {
+        self.double_panic
+    }
+
+    /// The time at which the thread panicked, for measuring how long ago it happened.  This
+    /// comes from the monitor's [`Clock`].  (The monitor doesn't use it to put records in order,
+    /// since a [`MockClock`] may give several panics the same time.)
+    ///
+    /// [`MockClock`]: struct.MockClock.html
+    /// [`Clock`]: trait.Clock.html
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// The time at which the thread panicked, according to the system clock.  Use this for
+    /// logging; use [`instant`] for elapsed-time calculations, since the system clock isn't
+    /// monotonic.
+    ///
+    /// [`instant`]: #method.instant
+    pub fn system_time(&self) -> SystemTime {
+        self.system_time
+    }
+
+    /// Make a record of the current thread's panic.  This must be called from inside the hook,
+    /// since that's the only time the payload is available.
+    fn capture(info: &panic::PanicHookInfo, thread_number: u64, backtraces: Option<bool>,
+        instant: Instant) -> PanicRecord
+    {
+        let system_time = SystemTime::now();
+        let backtrace = capture_backtrace(backtraces);
+        let double_panic = unwinding() && backtrace.as_ref().is_some_and(|bt| in_drop(bt));
+        PanicRecord {
+            thread: thread::current(),
+            thread_number,
+            payload: Payload::capture(info.payload()),
+            location: info.location().map(|l| (l.file().to_string(), l.line(), l.column())),
+            backtrace,
+            context: current_context(),
+            class: None,  // Filled in by `PanicMonitor::record`
+            double_panic,
+            instant,
+            system_time,
+            seq: 0,  // Filled in by `PanicMonitor::record`
+        }
+    }
+}
+
+/// Describes the panic, like the default panic hook does:
+///
+/// ```text
+/// thread 'worker-3' (#7) panicked at src/worker.rs:12:5: connection reset
+/// ```
+///
+/// where `#7` is the [`thread_number`].
+///
+/// [`thread_number`]: #method.thread_number
+impl fmt::Display for PanicRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.thread.name().unwrap_or("<unnamed>");
+        write!(f, "thread '{}' (#{}) panicked", name, self.thread_number)?;
+        if let Some((file, line, col)) = self.location() {
+            write!(f, " at {}:{}:{}", file, line, col)?;
+        }
+        write!(f, ": {}", self.message())
+    }
+}
+
+/// A `PanicRecord` can be used as an error, so that a supervisor can hand a worker's panic on to
+/// the usual error-handling machinery: `return Err(record.into())`.
+impl Error for PanicRecord {}
+
+/// The value which a thread passed to [`panic`], as far as the monitor can make it out.
+///
+/// The payload of a panic can be of any type, but the hook only gets to see it as a `dyn Any`, so
+/// it can't be rendered or stored in general.  The monitor recognises string payloads (which is
+/// what `panic!` produces with a message), and records the fact that there was some other payload
+/// otherwise.  Rust doesn't make the name of the payload's type available at runtime, so there's
+/// no way to say more than that.
+///
+/// [`panic`]: https://doc.rust-lang.org/std/macro.panic.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Payload {
+    /// A `&str` or `String`, such as the message formatted by `panic!`.
+    Str(String),
+    /// Anything else, such as a value passed to [`panic_any`].
+    ///
+    /// [`panic_any`]: https://doc.rust-lang.org/std/panic/fn.panic_any.html
+    Other,
+}
+
+impl Payload {
+    /// Make what we can of a panic's payload.
+    fn capture(payload: &(dyn Any + Send)) -> Payload {
+        if let Some(s) = payload.downcast_ref::<&str>() {
+            Payload::Str(s.to_string())
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            Payload::Str(s.clone())
+        } else {
+            Payload::Other
+        }
+    }
+}
+
+/// Records are serialized as a struct with the fields `thread_number`, `thread_name`, `message`,
+/// `location` (a struct with fields `file`, `line` and `column`), `backtrace`, `context`, and
+/// `time`.
+#[cfg(feature = "serde")]
+impl Serialize for PanicRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PanicRecord", 7)?;
+        s.serialize_field("thread_number", &self.thread_number)?;
+        s.serialize_field("thread_name", &self.thread.name())?;
+        s.serialize_field("message", self.message())?;
+        s.serialize_field("location", &self.location().map(Location::from))?;
+        s.serialize_field("backtrace", &self.backtrace)?;
+        s.serialize_field("context", &self.context)?;
+        s.serialize_field("time", &self.system_time)?;
+        s.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct Location<'a> {
+    file: &'a str,
+    line: u32,
+    column: u32,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> From<(&'a str, u32, u32)> for Location<'a> {
+    fn from((file, line, column): (&'a str, u32, u32)) -> Location<'a> {
+        Location { file, line, column }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Location<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Location", 3)?;
+        s.serialize_field("file", self.file)?;
+        s.serialize_field("line", &self.line)?;
+        s.serialize_field("column", &self.column)?;
+        s.end()
+    }
 }
 
 impl PanicMonitor {
     /// Create a new `PanicMonitor`.
     ///
-    /// Call this inside a [`lazy_static`] block.  You must call [`init`] after this.
+    /// This is a `const fn`, so you can use it to initialise a `static`.  You must call [`init`]
+    /// after this.  For a monitor with non-default settings, use [`builder`].
     ///
+    /// [`builder`]: #method.builder
     /// [`init`]: #method.init
-    /// [`lazy_static`]: https://docs.rs/lazy_static/1.0.0/lazy_static/macro.lazy_static.html
-    pub fn new() -> PanicMonitor {
+    pub const fn new() -> PanicMonitor {
         PanicMonitor {
-            panicked: Mutex::new(HashMap::new()),
-            cvar: Condvar::new(),
+            panicked: RwLock::new(new_map()),
+            waiters: Mutex::new(Waiters { by_thread: new_map(), any: Vec::new() }),
+            waiter_count: AtomicUsize::new(0),
+            panic_count: AtomicUsize::new(0),
+            thread_panic_counts: Mutex::new(new_map()),
+            next_seq: AtomicU64::new(0),
+            max_history: None,
+            backtraces: None,
+            chain: true,
+            abort_after: None,
+            clock: &SystemClock,
+            recent: Mutex::new(VecDeque::new()),
+            callbacks: RwLock::new(Vec::new()),
+            wait_callbacks: RwLock::new(Vec::new()),
+            classifier: RwLock::new(None),
+            wakeups: AtomicU64::new(0),
+            panic_wakeups: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+            #[cfg(feature = "log")]
+            log_panics: false,
+            #[cfg(feature = "tracing")]
+            trace_panics: false,
+            #[cfg(feature = "metrics")]
+            record_metrics: false,
+            spawned: Mutex::new(Vec::new()),
+            owned: Mutex::new(Vec::new()),
+            hook: Mutex::new(None),
+            warned_uninitialized: AtomicBool::new(false),
+            thread_numbers: Mutex::new(new_map()),
+            next_thread_number: AtomicU64::new(0),
+            subscribers: Mutex::new(Vec::new()),
+            wakers: Mutex::new(Vec::new()),
         }
     }
 
+    /// Start building a `PanicMonitor` with non-default settings.  See [`PanicMonitorBuilder`].
+    ///
+    /// [`PanicMonitorBuilder`]: struct.PanicMonitorBuilder.html
+    pub const fn builder() -> PanicMonitorBuilder {
+        PanicMonitorBuilder::new()
+    }
+
+    /// Create a new `PanicMonitor` which remembers at most `max` panics.
+    ///
+    /// When a panic would take the number of records above `max`, the oldest record is thrown
+    /// away.  This keeps memory usage bounded in long-running processes.  Note that a [`wait`] on
+    /// a thread whose record has been evicted will block as if the thread had never panicked.
+    /// (Thread IDs are never reused, so an evicted ID will only be seen again if that same thread
+    /// panics again.)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero, since the monitor would throw every record away (or, in a
+    /// `static`, fails to compile).
+    ///
+    /// [`wait`]: #method.wait
+    pub const fn with_capacity(max: usize) -> PanicMonitor {
+        assert!(max > 0, "with_capacity: max must be at least 1");
+        let mut monitor = PanicMonitor::new();
+        monitor.max_history = Some(max);
+        monitor
+    }
+
+    /// Emit a [`log`] error every time a thread panics (or not).  The default is not to.
+    ///
+    /// The message includes the thread's name, the location of the panic, and the panic message,
+    /// and says whether it was a [double panic].  The previously-installed panic hook is still
+    /// called as normal (unless the monitor was built with [`chain_previous_hook(false)`]).
+    ///
+    /// This method is only available with the `log` feature.
+    ///
+    /// [`chain_previous_hook(false)`]: struct.PanicMonitorBuilder.html#method.chain_previous_hook
+    /// [double panic]: struct.PanicRecord.html#method.is_double_panic
+    /// [`log`]: https://docs.rs/log
+    #[cfg(feature = "log")]
+    pub const fn log_panics(mut self, log_panics: bool) -> PanicMonitor {
+        self.log_panics = log_panics;
+        self
+    }
+
+    /// Emit a [`tracing`] error event every time a thread panics (or not).  The default is not
+    /// to.
+    ///
+    /// The event has the fields `thread.name`, `thread.id` (the [`thread_number`]),
+    /// `panic.message`, `panic.location`, and `panic.double` (see
+    /// [`PanicRecord::is_double_panic`]).  It's emitted before the previously-installed panic hook
+    /// is called.
+    ///
+    /// This method is only available with the `tracing` feature.
+    ///
+    /// [`PanicRecord::is_double_panic`]: struct.PanicRecord.html#method.is_double_panic
+    /// [`tracing`]: https://docs.rs/tracing
+    /// [`thread_number`]: struct.PanicRecord.html#method.thread_number
+    #[cfg(feature = "tracing")]
+    pub const fn trace_panics(mut self, trace_panics: bool) -> PanicMonitor {
+        self.trace_panics = trace_panics;
+        self
+    }
+
+    /// Report panics to the [`metrics`] recorder (or not).  The default is not to.
+    ///
+    /// Every panic increments the counter `panic_monitor.panics_total`, labelled with the
+    /// panicking thread's `thread` name, and sets the gauge `panic_monitor.panicked_threads` to
+    /// the [`panicked_thread_count`].  Panics caught by [`catch_unwind`] aren't counted.
+    ///
+    /// This method is only available with the `metrics` feature.
+    ///
+    /// [`metrics`]: https://docs.rs/metrics
+    /// [`catch_unwind`]: fn.catch_unwind.html
+    /// [`panicked_thread_count`]: #method.panicked_thread_count
+    #[cfg(feature = "metrics")]
+    pub const fn record_metrics(mut self, record_metrics: bool) -> PanicMonitor {
+        self.record_metrics = record_metrics;
+        self
+    }
+
     /// Initialise the `PanicMonitor`.
     ///
     /// Call this method as early as you can: a thread which panics before the `PanicMonitor` is
-    /// initialised will not trigger wake-ups.  Calling `init` multiple times is relatively
-    /// harmless.
-    //
-    // If you need to uninstall some existing handlers by calling `std::panic::set_hook(|_| {})`,
-    // or something, you can call `init` again afterwards to re-add `PanicMonitor`'s hook.
+    /// initialised will not trigger wake-ups.  Calling `init` more than once does nothing; if the
+    /// hook has been removed in the meantime (eg. by `std::panic::set_hook`), use [`reinstall`].
+    /// Calling it while the thread is panicking (eg. from a `Drop` impl) does nothing either,
+    /// since std doesn't allow the hook to be changed then.
+    ///
+    /// The monitor's hook records each panic and then calls the previously-installed hook (see
+    /// [`init_after`] for the other way round).  So if you use another crate which installs a
+    /// panic hook, such as `human-panic` or `color-eyre`, install its hook *first*, and then call
+    /// `init`: hooks which are installed later replace the monitor's, unless they take care to
+    /// call it.
+    ///
+    /// [`init_after`]: #method.init_after
+    /// [`reinstall`]: #method.reinstall
     pub fn init(&'static self) {
-        // Install a panic hook which makes a record of the panicking thread and notifies all
-        // threads waiting on the PanicMonitor
-        let hook = panic::take_hook();
-        panic::set_hook(Box::new(move|x| {
-            let mut panicked = self.panicked.lock().expect(POISON_MSG);
-            let current = thread::current();
-            panicked.insert(current.id(), current);
-            self.cvar.notify_all();
-            hook(x);
-        }));
+        self.install(HookTarget::Static(self), true);
+    }
+
+    /// The same as [`init`]: panics are recorded *before* the previously-installed hook is
+    /// called.  This is spelled out for symmetry with [`init_after`].
+    ///
+    /// [`init`]: #method.init
+    /// [`init_after`]: #method.init_after
+    pub fn init_before(&'static self) {
+        self.init();
+    }
+
+    /// Like [`init`], but panics are recorded *after* the previously-installed hook has been
+    /// called, rather than before.
+    ///
+    /// Recording first wakes the supervisors as early as possible.  But a supervisor might react
+    /// to a panic by ending the process, and then the previous hook may not get to finish (eg. to
+    /// print a crash report).  Use `init_after` if the previous hook's output matters more than
+    /// waking up promptly.  As with `init`, the other crate's hook should be installed first.
+    ///
+    /// [`init`]: #method.init
+    pub fn init_after(&'static self) {
+        self.install(HookTarget::Static(self), false);
+    }
+
+    /// Move the `PanicMonitor` onto the heap and leak it, so that it can be [`init`]ialised.
+    ///
+    /// This is for monitors which are created at runtime (eg. after reading some configuration),
+    /// rather than in a `static`.  The memory is never freed, so only do this once; if you want
+    /// the monitor to be freed when you're done with it, use [`new_arc`] and [`init_arc`]
+    /// instead.
+    ///
+    /// ```
+    /// # use panic_monitor::PanicMonitorBuilder;
+    /// let monitor = PanicMonitorBuilder::new().max_history(10).build().leak();
+    /// monitor.init();
+    /// ```
+    ///
+    /// [`init`]: #method.init
+    /// [`init_arc`]: #method.init_arc
+    /// [`new_arc`]: #method.new_arc
+    pub fn leak(self) -> &'static PanicMonitor {
+        Box::leak(Box::new(self))
+    }
+
+    /// Create a new `PanicMonitor` which can be shared without putting it in a `static`.  You
+    /// must call [`init_arc`] after this.
+    ///
+    /// [`init_arc`]: #method.init_arc
+    pub fn new_arc() -> Arc<PanicMonitor> {
+        Arc::new(PanicMonitor::new())
+    }
+
+    /// Like [`init`], but for a `PanicMonitor` which lives in an `Arc`.
+    ///
+    /// The installed hook only holds a weak reference to the monitor, so the monitor is freed as
+    /// normal when the last `Arc` is dropped; after that, the hook does nothing (except for
+    /// calling the previously-installed hook).
+    ///
+    /// [`init`]: #method.init
+    pub fn init_arc(self: &Arc<Self>) {
+        self.install(HookTarget::Weak(Arc::downgrade(self)), true);
+    }
+
+    /// Install the `PanicMonitor`'s hook again, on top of whatever hook is currently installed.
+    ///
+    /// Use this if something has replaced the panic hook since the monitor was initialised.  If
+    /// the monitor's hook is still installed, this will cause every panic to be recorded twice.
+    /// This does nothing if the monitor isn't initialised.
+    pub fn reinstall(&self) {
+        if thread::panicking() { return; }  // `set_hook` would panic
+        if let Some(ref installed) = *lock(&self.hook) {
+            let (target, live) = (installed.target.clone(), installed.live.clone());
+            install_hook(target, live, self.chain, installed.record_first);
+        }
+    }
+
+    /// Remove the `PanicMonitor`'s hook, restoring the hook which was installed before [`init`]
+    /// was called.  Panics will no longer be recorded, although the existing records are kept.
+    ///
+    /// Any hooks which were installed on top of the monitor's are removed too.  (If they're still
+    /// in use somewhere, the monitor's hook will just pass panics straight through to the original
+    /// hook.)  After calling this, [`init`] can be used to install the hook again.  This does
+    /// nothing if the monitor isn't initialised.
+    ///
+    /// [`init`]: #method.init
+    pub fn uninstall(&self) {
+        let installed = match lock(&self.hook).take() { Some(x) => x, None => return };
+        installed.live.store(false, Ordering::SeqCst);
+        // `set_hook` would panic, but the hook passes everything through once it isn't live anyway
+        if thread::panicking() { return; }
+        let previous = installed.previous;
+        panic::set_hook(Box::new(move |x| previous(x)));
+    }
+
+    /// Is the `PanicMonitor`'s hook installed?  This is true after [`init`] (or [`init_arc`]), and
+    /// false again after [`uninstall`].
+    ///
+    /// A monitor which isn't initialised doesn't record any panics, so waiting on it will block
+    /// forever.  To help diagnose this, the first call to a method like [`wait`] on an
+    /// uninitialised monitor prints a warning in debug builds (and logs one, with the `log`
+    /// feature).
+    ///
+    /// [`init`]: #method.init
+    /// [`init_arc`]: #method.init_arc
+    /// [`uninstall`]: #method.uninstall
+    /// [`wait`]: #method.wait
+    pub fn is_initialized(&self) -> bool {
+        lock(&self.hook).is_some()
+    }
+
+    /// Complain (once) if we're about to wait for a panic which can never be recorded.
+    fn warn_if_uninitialized(&self) {
+        if self.is_initialized() || self.warned_uninitialized.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let msg = "waiting on a PanicMonitor which hasn't been initialised: \
+                   panics won't be recorded until `init` is called";
+        #[cfg(feature = "log")]
+        warn!("{}", msg);
+        if cfg!(debug_assertions) { eprintln!("panic_monitor: {}", msg); }
+    }
+
+    fn install(&self, target: HookTarget, record_first: bool) {
+        if thread::panicking() { return; }  // `set_hook` would panic
+        let mut hook = lock(&self.hook);
+        if hook.is_some() { return; }  // Already installed
+        let live = Arc::new(AtomicBool::new(true));
+        let previous = install_hook(target.clone(), live.clone(), self.chain, record_first);
+        *hook = Some(Installed { target, live, previous, record_first });
+    }
+
+    /// Spawn a new thread with the given name, returning its handle and ID.  The thread's ID is
+    /// added to the list returned by [`spawned`].
+    ///
+    /// [`spawned`]: #method.spawned
+    pub fn spawn<F, T>(&self, name: &str, f: F) -> io::Result<(JoinHandle<T>, ThreadId)>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawn_with(thread::Builder::new().name(name.to_string()), f)
+    }
+
+    /// Like [`spawn`], but takes a [`thread::Builder`], so you can set the thread's stack size.
+    ///
+    /// [`spawn`]: #method.spawn
+    /// [`thread::Builder`]: https://doc.rust-lang.org/std/thread/struct.Builder.html
+    pub fn spawn_with<F, T>(&self, builder: thread::Builder, f: F)
+        -> io::Result<(JoinHandle<T>, ThreadId)>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        // The thread holds the only strong reference, so we can tell when it's finished
+        let alive = Arc::new(());
+        let weak = Arc::downgrade(&alive);
+        let finished = CancelToken::new();
+        let token = finished.clone();
+        let h = builder.spawn(move || {
+            let _alive = alive;
+            let x = f();
+            token.cancel();  // Only reached if `f` didn't panic
+            x
+        })?;
+        let tid = h.thread().id();
+        self.assign_thread_number(tid);
+        lock(&self.spawned).push(Spawned { tid, alive: weak, finished });
+        Ok((h, tid))
+    }
+
+    /// The IDs of all the threads which have been spawned with [`spawn`] or [`spawn_with`], in the
+    /// order they were spawned.  This is handy as a watch list.  Threads which have been removed
+    /// by [`prune`] aren't included.
+    ///
+    /// [`prune`]: #method.prune
+    /// [`spawn`]: #method.spawn
+    /// [`spawn_with`]: #method.spawn_with
+    pub fn spawned(&self) -> Vec<ThreadId> {
+        lock(&self.spawned).iter().map(|s| s.tid).collect()
+    }
+
+    /// Like [`spawn`], but the monitor keeps hold of the thread's `JoinHandle`, so that it can be
+    /// joined later by [`join_all`].  Any value returned by `f` is thrown away.
+    ///
+    /// [`join_all`]: #method.join_all
+    /// [`spawn`]: #method.spawn
+    pub fn spawn_owned<F>(&self, name: &str, f: F) -> io::Result<ThreadId>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let (h, tid) = self.spawn(name, f)?;
+        lock(&self.owned).push(h);
+        Ok(tid)
+    }
+
+    /// Join all the threads spawned with [`spawn_owned`], in the order they were spawned, and
+    /// report how each one ended.  This is the teardown half of using the monitor as a simple
+    /// thread pool.
+    ///
+    /// This blocks until every one of the threads has finished, so tell the threads to stop (eg.
+    /// with a [`CancelToken`]) before calling it.  The handles are taken from the monitor up
+    /// front: threads which are spawned while `join_all` is running are left for the next call.
+    /// If `join_all` is called from one of the owned threads, that thread isn't joined (since it
+    /// would never finish), and its handle is kept.
+    ///
+    /// [`CancelToken`]: struct.CancelToken.html
+    /// [`spawn_owned`]: #method.spawn_owned
+    pub fn join_all(&self) -> Vec<(ThreadId, Outcome)> {
+        let me = thread::current().id();
+        let (handles, mine): (Vec<_>, Vec<_>) =
+            lock(&self.owned).drain(..).partition(|h| h.thread().id() != me);
+        lock(&self.owned).extend(mine);
+        handles.into_iter().map(|h| {
+            let thread = h.thread().clone();
+            let outcome = match h.join() {
+                Ok(()) => Outcome::Finished,
+                Err(_) => Outcome::Panicked(thread.clone()),
+            };
+            (thread.id(), outcome)
+        }).collect()
     }
 
     /// Block the current thread until one of the watched threads panics.  The returned vector is
@@ -126,60 +963,2053 @@ impl PanicMonitor {
     ///
     /// Note that this function returns as soon as one or more of the threads on the watch list has
     /// panicked.  This means that if you specify a thread which has already panicked, this
-    /// function will return immediately.  Think of it as level-triggered, not edge-triggered.
-    pub fn wait(&self, watch_list: &[ThreadId]) -> Vec<Thread> {
-        let mut watched_panicked = vec![];
-        let mut panicked = self.panicked.lock().expect(POISON_MSG);
-        loop {
-            for tid in watch_list {
-                if let Some(t) = panicked.get(tid) {
-                    watched_panicked.push(t.clone());
-                }
-            }
-            if watched_panicked.len() > 0 { return watched_panicked; }
-            panicked = self.cvar.wait(panicked).expect(POISON_MSG);
-        }
+    /// function will return immediately.  Think of it as level-triggered, not edge-triggered.  (If
+    /// you'd rather not be told about the same panic twice, use a [`cursor`].)
+    ///
+    /// If the watch list is empty (eg. [`ALL_THREADS`]), *all* threads are watched: this function
+    /// returns as soon as any thread panics, or immediately if some thread has already panicked.
+    /// The same goes for the other methods which take a watch list, with the exception of
+    /// [`wait_all`].
+    ///
+    /// The watch list can be anything which implements [`WatchList`]: a slice, an array, a `Vec`,
+    /// a `HashSet`, or an iterator of `ThreadId`s, or of [`Thread`]s or [`JoinHandle`]s (see
+    /// [`Watchable`]).  This also goes for the other methods which take a watch list, except for
+    /// the async and poll-style ones and [`check_into`], which take a slice of `ThreadId`s so they
+    /// needn't allocate.
+    ///
+    /// The returned threads are in the same order as the watch list (or, for an empty watch list,
+    /// in the order they panicked).  Each thread is returned at most once, even if it appears in
+    /// the watch list more than once.  This also goes for the other methods which take a watch
+    /// list.
+    ///
+    /// [`ALL_THREADS`]: constant.ALL_THREADS.html
+    /// [`check_into`]: #method.check_into
+    /// [`cursor`]: #method.cursor
+    /// [`JoinHandle`]: https://doc.rust-lang.org/std/thread/struct.JoinHandle.html
+    /// [`Thread`]: https://doc.rust-lang.org/std/thread/struct.Thread.html
+    /// [`wait_all`]: #method.wait_all
+    /// [`Watchable`]: trait.Watchable.html
+    /// [`WatchList`]: trait.WatchList.html
+    pub fn wait<W: WatchList>(&self, watch_list: W) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        self.wait_with(watch_list, |r| r.thread.clone())
     }
 
-    /// Block the current thread until one of the watched threads panic, or the timeout expires.
-    /// The returned vector is empty if and only if the timeout expired.
+    /// Like [`wait`], but only returns one thread: the first one in the watch list which has
+    /// panicked (or, for an empty watch list, the one whose most recent panic came first).
     ///
-    /// See [`wait`] for more information.
+    /// Use this when you only care that *something* has died, to save allocating a vector.
     ///
     /// [`wait`]: #method.wait
-    pub fn wait_timeout(&self, watch_list: &[ThreadId], mut dur: Duration) -> Vec<Thread> {
-        let mut watched_panicked = vec![];
-        let mut panicked = self.panicked.lock().expect(POISON_MSG);
-        loop {
-            for tid in watch_list {
-                if let Some(t) = panicked.get(tid) {
-                    watched_panicked.push(t.clone());
-                }
-            }
-            if watched_panicked.len() > 0 { return watched_panicked; }
-            let now = Instant::now();
-            let (guard, res) = self.cvar.wait_timeout(panicked, dur).expect(POISON_MSG);
-            let elapsed = now.elapsed();
-            panicked = guard;
-            if res.timed_out() || elapsed >= dur { return vec![]; }
-            dur -= elapsed; // safe because ^
-        }
+    pub fn wait_first<W: WatchList>(&self, watch_list: W) -> Thread {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        let thread = self.block_until(watch_list, |panicked| {
+            first(panicked, watch_list).map(|r| r.thread.clone())
+        });
+        self.observe(start, [&thread]);
+        thread
     }
 
-    /// Check if any of the specified threads have panicked.  This function may block, but only
-    /// very briefly.  The returned vector may be empty.
+    /// Block until the monitor has recorded a panic of the given thread, and return the most
+    /// recent one.  This is `wait_first([tid])`, but it says what it's for (and returns the
+    /// record).  It's handy in tests which need to be sure that a panic has been processed before
+    /// they carry on.
     ///
-    /// See [`wait`] for more information.
+    /// By the time a panic has been recorded, the monitor's waiters have been notified (or are
+    /// about to be), and the `on_panic` callbacks have run.  A panic is recorded by the hook,
+    /// before the thread starts to unwind; so `tid` may be the current thread, as long as it has
+    /// already panicked, eg. inside `std::panic::catch_unwind`.  (Panics caught by this crate's
+    /// [`catch_unwind`] aren't recorded, so waiting for one of those blocks forever.)
+    ///
+    /// [`catch_unwind`]: fn.catch_unwind.html
+    pub fn wait_for_record(&self, tid: ThreadId) -> PanicRecord {
+        let start = Instant::now();
+        let record = self.block_until(&[tid], |panicked| {
+            panicked.get(&tid).and_then(|rs| rs.last()).cloned()
+        });
+        self.observe(start, [&record.thread]);
+        record
+    }
+
+    /// Like [`wait`], but for a watch list whose length is known at compile time, and without
+    /// allocating a vector for the result.
+    ///
+    /// The result lines up with the watch list: element `i` is the thread `watch_list[i]` if it
+    /// has panicked, and `None` otherwise.  At least one element is `Some`.  (So unlike with
+    /// [`wait`], a thread which appears more than once in the watch list is returned more than
+    /// once.)  The watch list mustn't be empty: that's a compile error.
+    ///
+    /// If one of the threads has already panicked, this doesn't allocate at all.  Otherwise,
+    /// blocking involves a small allocation to register with the monitor.
     ///
     /// [`wait`]: #method.wait
-    pub fn check(&self, watch_list: &[ThreadId]) -> Vec<Thread> {
-        let mut watched_panicked = vec![];
-        let panicked = self.panicked.lock().expect(POISON_MSG);
-        for tid in watch_list {
-            if let Some(t) = panicked.get(tid) {
-                watched_panicked.push(t.clone());
-            }
-        }
-        watched_panicked
+    pub fn wait_fixed<const N: usize>(&self, watch_list: &[ThreadId; N]) -> [Option<Thread>; N] {
+        const { assert!(N > 0, "the watch list of `wait_fixed` can't be empty") };
+        let start = Instant::now();
+        let threads: [Option<Thread>; N] = self.block_until(watch_list, |panicked| {
+            let threads = std::array::from_fn(|i| {
+                panicked.get(&watch_list[i]).and_then(|rs| rs.last()).map(|r| r.thread.clone())
+            });
+            if threads.iter().any(Option::is_some) { Some(threads) } else { None }
+        });
+        self.observe(start, threads.iter().flatten());
+        threads
     }
-}
+
+    /// Block until one of the watched threads panics, and then panic too, with a message which
+    /// describes the thread and its panic.  (If several have panicked, the first one in the watch
+    /// list is described.)
+    ///
+    /// This is for supervisors whose only sensible reaction to losing a worker is to bring the
+    /// whole process down, and saves writing out the equivalent `panic!` by hand.  The message is
+    /// the [`PanicRecord`]'s `Display` output.
+    ///
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    #[track_caller]
+    pub fn wait_and_propagate<W: WatchList>(&self, watch_list: W) -> ! {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        let record = self.block_until(watch_list, |panicked| first(panicked, watch_list).cloned());
+        self.observe(start, [&record.thread]);
+        panic!("{}", record)
+    }
+
+    /// Like [`wait`], but returns a [`PanicRecord`] for each of the panicked threads.
+    ///
+    /// If a thread has panicked more than once, only its most recent panic is returned.  See
+    /// [`history`] for the full list.
+    ///
+    /// [`history`]: #method.history
+    /// [`wait`]: #method.wait
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    pub fn wait_records<W: WatchList>(&self, watch_list: W) -> Vec<PanicRecord> {
+        let watch_list = &watch_list.into_ids();
+        self.wait_with(watch_list, PanicRecord::clone)
+    }
+
+    /// Block the current thread until one of the watched threads panic, or the timeout expires.
+    /// The returned vector is empty if and only if the timeout expired.
+    ///
+    /// As with [`wait`], an empty watch list watches every thread: this returns as soon as any
+    /// thread panics (or immediately, if one already has), and only sleeps for the whole timeout
+    /// if no thread panics in the meantime.  See [`wait`] for more information.
+    ///
+    /// [`wait`]: #method.wait
+    pub fn wait_timeout<W: WatchList>(&self, watch_list: W, dur: Duration) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        self.wait_timeout_with(watch_list, dur, |r| r.thread.clone())
+    }
+
+    /// Like [`wait_timeout`], but returns a [`PanicRecord`] for each of the panicked threads.
+    ///
+    /// [`wait_timeout`]: #method.wait_timeout
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    pub fn wait_timeout_records<W: WatchList>(&self, watch_list: W, dur: Duration)
+        -> Vec<PanicRecord>
+    {
+        let watch_list = &watch_list.into_ids();
+        self.wait_timeout_with(watch_list, dur, PanicRecord::clone)
+    }
+
+    /// Like [`wait_timeout`], but with the timeout given in milliseconds.  This is handy when the
+    /// timeout comes from a config file.
+    ///
+    /// [`wait_timeout`]: #method.wait_timeout
+    pub fn wait_timeout_ms<W: WatchList>(&self, watch_list: W, ms: u64) -> Vec<Thread> {
+        self.wait_timeout(watch_list, Duration::from_millis(ms))
+    }
+
+    /// Like [`wait_timeout_records`], but with the timeout given in milliseconds.
+    ///
+    /// [`wait_timeout_records`]: #method.wait_timeout_records
+    pub fn wait_timeout_records_ms<W: WatchList>(&self, watch_list: W, ms: u64)
+        -> Vec<PanicRecord>
+    {
+        self.wait_timeout_records(watch_list, Duration::from_millis(ms))
+    }
+
+    /// Like [`wait_timeout`], but also returns how much of the timeout is left.  This makes it
+    /// easy to share a single budget between several operations.
+    ///
+    /// If the timeout expired, the returned vector is empty and the remaining time is zero.
+    ///
+    /// [`wait_timeout`]: #method.wait_timeout
+    pub fn wait_timeout_remaining<W: WatchList>(&self, watch_list: W, dur: Duration)
+        -> (Vec<Thread>, Duration)
+    {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        let threads = self.wait_timeout_with(watch_list, dur, |r| r.thread.clone());
+        if threads.is_empty() { return (threads, Duration::from_secs(0)); }
+        (threads, dur.saturating_sub(start.elapsed()))
+    }
+
+    /// Block the current thread until one of the watched threads panic, or the deadline passes.
+    /// The returned vector is empty if and only if the deadline passed.
+    ///
+    /// If the deadline has already passed, this is equivalent to [`check`].  See [`wait`] for
+    /// more information.
+    ///
+    /// [`check`]: #method.check
+    /// [`wait`]: #method.wait
+    pub fn wait_deadline<W: WatchList>(&self, watch_list: W, deadline: Instant) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        if deadline <= start { return self.check_with(watch_list, |r| r.thread.clone()); }
+        let threads = self.block_until_deadline(watch_list, deadline, |panicked| {
+            non_empty(collect(panicked, watch_list, |r| r.thread.clone()))
+        }).unwrap_or_default();
+        self.observe(start, &threads);
+        threads
+    }
+
+    /// Like [`wait_deadline`], but returns a [`PanicRecord`] for each of the panicked threads.
+    ///
+    /// [`wait_deadline`]: #method.wait_deadline
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    pub fn wait_deadline_records<W: WatchList>(&self, watch_list: W, deadline: Instant)
+        -> Vec<PanicRecord>
+    {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        if deadline <= start { return self.check_with(watch_list, PanicRecord::clone); }
+        let records = self.block_until_deadline(watch_list, deadline, |panicked| {
+            non_empty(collect(panicked, watch_list, PanicRecord::clone))
+        }).unwrap_or_default();
+        self.observe(start, records.iter().map(PanicRecord::thread));
+        records
+    }
+
+    /// Block the current thread until *all* of the watched threads have panicked, and return
+    /// them.
+    ///
+    /// Threads which appear in the watch list more than once are only returned once.  If the watch
+    /// list is empty, this function returns an empty vector immediately.
+    pub fn wait_all<W: WatchList>(&self, watch_list: W) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        if watch_list.is_empty() { return vec![]; }
+        let mut unique = Vec::with_capacity(watch_list.len());
+        for tid in watch_list {
+            if !unique.contains(tid) { unique.push(*tid); }
+        }
+        let start = Instant::now();
+        let threads = self.block_until(&unique, |panicked| {
+            if unique.iter().all(|tid| panicked.contains_key(tid)) {
+                Some(collect(panicked, &unique, |r| r.thread.clone()))
+            } else {
+                None
+            }
+        });
+        self.observe(start, &threads);
+        threads
+    }
+
+    /// Block the current thread until at least `k` of the watched threads have panicked, and
+    /// return all the watched threads which have panicked (so there are at least `k` of them).
+    ///
+    /// This sits between [`wait`] (which is like `k == 1`) and [`wait_all`] (which is like `k`
+    /// being the length of the watch list), and is handy for quorum logic.  Threads which appear
+    /// in the watch list more than once are only counted once.  If `k == 0`, this returns an empty
+    /// vector immediately.  As usual, an empty watch list watches every thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is greater than the number of distinct threads in a non-empty watch list,
+    /// since it would block forever.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`wait_all`]: #method.wait_all
+    pub fn wait_count<W: WatchList>(&self, watch_list: W, k: usize) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        if k == 0 { return vec![]; }
+        if !watch_list.is_empty() {
+            let distinct = watch_list.iter().enumerate()
+                .filter(|&(i, tid)| !watch_list[..i].contains(tid))
+                .count();
+            assert!(k <= distinct, "wait_count: can't wait for {} of {} threads", k, distinct);
+        }
+        let start = Instant::now();
+        let threads = self.block_until(watch_list, |panicked| {
+            let threads = collect(panicked, watch_list, |r| r.thread.clone());
+            if threads.len() >= k { Some(threads) } else { None }
+        });
+        self.observe(start, &threads);
+        threads
+    }
+
+    /// Block the current thread until one of the threads in the group panics.  This is like
+    /// [`wait`] with the group's members as the watch list.
+    ///
+    /// The members are read once, when this method is called: threads which are added to the group
+    /// later aren't watched.  If the group is empty, this returns an empty vector immediately
+    /// (whereas [`wait`] with an empty watch list would watch every thread).
+    ///
+    /// [`wait`]: #method.wait
+    pub fn wait_group(&self, group: &ThreadGroup) -> Vec<Thread> {
+        let watch_list = group.ids();
+        if watch_list.is_empty() { return vec![]; }
+        self.wait(&watch_list)
+    }
+
+    /// Block the current thread until one of the threads in `handle` panics.  This is like
+    /// [`wait`], except that the watch list can be changed while this is blocked: threads which
+    /// are added to the handle are watched from then on, and threads which are removed are no
+    /// longer watched.
+    ///
+    /// If the handle is empty, this waits until a thread is added to it and then panics (rather
+    /// than watching every thread, as [`wait`] would).
+    ///
+    /// [`wait`]: #method.wait
+    pub fn wait_handle(&self, handle: &WatchHandle) -> Vec<Thread> {
+        let start = Instant::now();
+        loop {
+            let (watch_list, changed) = handle.snapshot();
+            let threads = self.block(&watch_list, None, Some(&changed), |panicked| {
+                if watch_list.is_empty() { return None; }
+                non_empty(collect(panicked, &watch_list, |r| r.thread.clone()))
+            });
+            if let Some(threads) = threads {
+                self.observe(start, &threads);
+                return threads;
+            }
+        }
+    }
+
+    /// Block the current thread until one of the threads in the watch list produced by `f`
+    /// panics.  `f` is called again every time any thread panics, so threads which show up in its
+    /// watch list while this is blocked get watched too.
+    ///
+    /// This is a lighter-weight alternative to [`wait_handle`]: there's no shared handle to keep
+    /// up to date, but `f` is only re-evaluated when some thread panics.  So a thread which joins
+    /// the list after it has already panicked is only noticed at the next panic.  If `f` returns
+    /// an empty list, nothing is watched (rather than every thread, as with [`wait`]).
+    ///
+    /// `f` is evaluated while the monitor is locked, so it must be cheap, it must not panic, and
+    /// it must not call back into the monitor, or it may deadlock.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`wait_handle`]: #method.wait_handle
+    pub fn wait_dynamic<W: WatchList, F: Fn() -> W>(&self, f: F) -> Vec<Thread> {
+        let start = Instant::now();
+        let threads = self.block_until(&[], |panicked| {
+            let watch_list = f().into_ids();
+            if watch_list.is_empty() { return None; }
+            non_empty(collect(panicked, &watch_list, |r| r.thread.clone()))
+        });
+        self.observe(start, &threads);
+        threads
+    }
+
+    /// Block the current thread until a thread satisfying `pred` panics, and return all such
+    /// threads (in the order of their most recent panics).  The returned vector is always
+    /// non-empty.
+    ///
+    /// This is handy when threads are named by role: for instance, `|t| t.name().is_some_and(|n|
+    /// n.starts_with("worker-"))` matches all the workers, wherever they were spawned.  Like
+    /// [`wait`], this is level-triggered.
+    ///
+    /// `pred` is evaluated while the monitor is locked, every time any thread panics.  It must not
+    /// panic, and it must not call back into the monitor, or it may deadlock.
+    ///
+    /// [`wait`]: #method.wait
+    pub fn wait_filter<P: Fn(&Thread) -> bool>(&self, pred: P) -> Vec<Thread> {
+        let start = Instant::now();
+        let threads = self.block_until(&[], |panicked| {
+            non_empty(collect_matching(panicked, &pred, |r| r.thread.clone()))
+        });
+        self.observe(start, &threads);
+        threads
+    }
+
+    /// Block the current thread until a thread whose name is in `names` panics, and return all
+    /// such threads.  This is a shorthand for [`wait_filter`].
+    ///
+    /// Names needn't be unique, so there may be more than one thread returned for each name.
+    /// Unnamed threads never match (not even the name `"<unnamed>"`).
+    ///
+    /// [`wait_filter`]: #method.wait_filter
+    pub fn wait_named(&self, names: &[&str]) -> Vec<Thread> {
+        self.wait_filter(|t| t.name().is_some_and(|n| names.contains(&n)))
+    }
+
+    /// Like [`wait`], but gives up and returns `None` if `cancel` is cancelled first.  This lets
+    /// you shut down a supervisor which is waiting for its workers to panic.
+    ///
+    /// If the token has already been cancelled, this is equivalent to [`try_wait`].
+    ///
+    /// [`try_wait`]: #method.try_wait
+    /// [`wait`]: #method.wait
+    pub fn wait_cancellable<W: WatchList>(&self, watch_list: W, cancel: &CancelToken)
+        -> Option<Vec<Thread>>
+    {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        let threads = self.block(watch_list, None, Some(cancel), |panicked| {
+            non_empty(collect(panicked, watch_list, |r| r.thread.clone()))
+        });
+        if let Some(ref threads) = threads { self.observe(start, threads); }
+        threads
+    }
+
+    /// Block until the given thread either panics or returns normally.  This is like
+    /// [`JoinHandle::join`], but doesn't need the handle, and can be called from any number of
+    /// threads at once.
+    ///
+    /// The monitor only finds out about normal completion for threads which it spawned itself
+    /// (with [`spawn`] or [`spawn_with`]) and which haven't been [`prune`]d.  For any other
+    /// thread, this waits for a panic, just like [`wait`].  A thread which recovers from a panic
+    /// with [`catch_unwind`] and then returns counts as `Finished`.
+    ///
+    /// [`JoinHandle::join`]: https://doc.rust-lang.org/std/thread/struct.JoinHandle.html#method.join
+    /// [`catch_unwind`]: fn.catch_unwind.html
+    /// [`prune`]: #method.prune
+    /// [`spawn`]: #method.spawn
+    /// [`spawn_with`]: #method.spawn_with
+    /// [`wait`]: #method.wait
+    pub fn wait_done(&self, tid: ThreadId) -> Outcome {
+        let finished = lock(&self.spawned).iter()
+            .find(|s| s.tid == tid)
+            .map(|s| s.finished.clone());
+        let start = Instant::now();
+        let panicked = self.block(&[tid], None, finished.as_ref(), |panicked| {
+            panicked.get(&tid).and_then(|rs| rs.last()).map(|r| r.thread.clone())
+        });
+        match panicked {
+            Some(thread) => {
+                self.observe(start, [&thread]);
+                Outcome::Panicked(thread)
+            }
+            None => Outcome::Finished,
+        }
+    }
+
+    /// Check whether any of the watched threads have panicked, without blocking on the condition
+    /// variable.  Returns `None` if none of them have panicked yet; otherwise, the returned vector
+    /// is non-empty.
+    ///
+    /// This is the same as [`check`], but is more convenient for poll-style loops.
+    ///
+    /// [`check`]: #method.check
+    pub fn try_wait<W: WatchList>(&self, watch_list: W) -> Option<Vec<Thread>> {
+        let watch_list = &watch_list.into_ids();
+        non_empty(self.check_with(watch_list, |r| r.thread.clone()))
+    }
+
+    /// Like [`try_wait`], but returns a [`PanicRecord`] for each of the panicked threads.
+    ///
+    /// [`try_wait`]: #method.try_wait
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    pub fn try_wait_records<W: WatchList>(&self, watch_list: W) -> Option<Vec<PanicRecord>> {
+        let watch_list = &watch_list.into_ids();
+        non_empty(self.check_with(watch_list, PanicRecord::clone))
+    }
+
+    /// Check if any of the specified threads have panicked.  This function may block, but only
+    /// very briefly.  The returned vector may be empty.
+    ///
+    /// `check` never waits for a panic to happen.  It only takes a read lock on the monitor's
+    /// records, so the only thing it can end up waiting for is a panic which is in the middle of
+    /// being stored; the panic hook does all its slower work (capturing a backtrace, logging,
+    /// running [`on_panic`] callbacks) before taking the write lock.  So it's fine to call `check`
+    /// from latency-sensitive code, even while lots of threads are panicking.
+    ///
+    /// See [`wait`] for more information.
+    ///
+    /// [`on_panic`]: #method.on_panic
+    /// [`wait`]: #method.wait
+    pub fn check<W: WatchList>(&self, watch_list: W) -> Vec<Thread> {
+        let watch_list = &watch_list.into_ids();
+        self.check_with(watch_list, |r| r.thread.clone())
+    }
+
+    /// Like [`check`], but puts the panicked threads into `buf` instead of allocating a new
+    /// vector.  `buf` is cleared first.
+    ///
+    /// This is for supervisors which poll in a hot loop: by reusing the same buffer, they needn't
+    /// allocate on every iteration.  For the same reason, the watch list must be a slice.
+    ///
+    /// [`check`]: #method.check
+    pub fn check_into(&self, watch_list: &[ThreadId], buf: &mut Vec<Thread>) {
+        buf.clear();
+        let panicked = read(&self.panicked);
+        collect_into(&panicked, watch_list, |r| r.thread.clone(), buf);
+    }
+
+    /// Like [`check`], but only returns the IDs of the panicked threads.  This saves cloning a
+    /// `Thread` for each of them.
+    ///
+    /// [`check`]: #method.check
+    pub fn check_ids<W: WatchList>(&self, watch_list: W) -> Vec<ThreadId> {
+        let watch_list = &watch_list.into_ids();
+        self.check_with(watch_list, |r| r.thread.id())
+    }
+
+    /// Like [`check`], but only returns the names of the panicked threads (`None` for an unnamed
+    /// thread), in the same order.
+    ///
+    /// [`check`]: #method.check
+    pub fn check_names<W: WatchList>(&self, watch_list: W) -> Vec<Option<String>> {
+        let watch_list = &watch_list.into_ids();
+        self.check_with(watch_list, |r| r.thread.name().map(str::to_owned))
+    }
+
+    /// Like [`check`], but returns a [`PanicRecord`] for each of the panicked threads.
+    ///
+    /// [`check`]: #method.check
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    pub fn check_records<W: WatchList>(&self, watch_list: W) -> Vec<PanicRecord> {
+        let watch_list = &watch_list.into_ids();
+        self.check_with(watch_list, PanicRecord::clone)
+    }
+
+    /// Has the given thread panicked?  This is like [`check`], but doesn't allocate.
+    ///
+    /// [`check`]: #method.check
+    pub fn has_panicked(&self, tid: ThreadId) -> bool {
+        read(&self.panicked).contains_key(&tid)
+    }
+
+    /// Has the given thread ever panicked, even if its records have since been thrown away?
+    ///
+    /// Unlike [`has_panicked`], this is still true after the thread's records have been removed
+    /// by [`forget`], [`clear`], or the [`max_history`] limit, so it can answer "did this thread
+    /// ever crash?" in a post-mortem while the memory used by the records is reclaimed.  The
+    /// monitor only keeps a count for each thread (see [`panic_count_for`]), which is wiped by
+    /// [`reset`], or by [`prune`] once the thread has finished.
+    ///
+    /// [`clear`]: #method.clear
+    /// [`forget`]: #method.forget
+    /// [`has_panicked`]: #method.has_panicked
+    /// [`max_history`]: struct.PanicMonitorBuilder.html#method.max_history
+    /// [`panic_count_for`]: #method.panic_count_for
+    /// [`prune`]: #method.prune
+    /// [`reset`]: #method.reset
+    pub fn ever_panicked(&self, tid: ThreadId) -> bool {
+        lock(&self.thread_panic_counts).contains_key(&tid)
+    }
+
+    /// Have any of the watched threads panicked?  This is like [`check`], but doesn't allocate.
+    /// If the watch list is empty, returns true if any thread has panicked.
+    ///
+    /// [`check`]: #method.check
+    pub fn any_panicked<W: WatchList>(&self, watch_list: W) -> bool {
+        let watch_list = &watch_list.into_ids();
+        let panicked = read(&self.panicked);
+        if watch_list.is_empty() { return !panicked.is_empty(); }
+        watch_list.iter().any(|tid| panicked.contains_key(tid))
+    }
+
+    /// Wake `waker` the next time one of the watched threads panics.  The waker is woken at most
+    /// once; after that, the monitor forgets about it.
+    ///
+    /// This is a building block for executors which want to wait for panics without the `futures`
+    /// feature.  Registering a waker which [`will_wake`] the same task as one which is already
+    /// registered for the same watch list does nothing, so it's fine to call this on every poll.
+    /// You probably want [`poll_wait`], which doesn't miss panics which happen just before the
+    /// waker is registered.
+    ///
+    /// [`poll_wait`]: #method.poll_wait
+    /// [`will_wake`]: https://doc.rust-lang.org/std/task/struct.Waker.html#method.will_wake
+    pub fn register_waker(&self, watch_list: &[ThreadId], waker: Waker) {
+        let mut wakers = lock(&self.wakers);
+        let registered = wakers.iter().any(|(wl, entry)| match *entry {
+            WakerEntry::Once(ref w) => wl.as_slice() == watch_list && w.will_wake(&waker),
+            #[cfg(feature = "futures")]
+            WakerEntry::Future(_) => false,
+        });
+        if !registered { wakers.push((watch_list.to_vec(), WakerEntry::Once(waker))); }
+    }
+
+    /// Poll-style version of [`wait`], for writing futures by hand (eg. with
+    /// [`std::future::poll_fn`]).  Returns `Ready` if one of the watched threads has panicked;
+    /// otherwise, arranges for the task to be woken when one does (see [`register_waker`]) and
+    /// returns `Pending`.
+    ///
+    /// [`register_waker`]: #method.register_waker
+    /// [`std::future::poll_fn`]: https://doc.rust-lang.org/std/future/fn.poll_fn.html
+    /// [`wait`]: #method.wait
+    pub fn poll_wait(&self, watch_list: &[ThreadId], cx: &mut Context) -> Poll<Vec<Thread>> {
+        // Hold the lock while registering the waker, so we can't miss a panic
+        let panicked = read(&self.panicked);
+        if let Some(ts) = non_empty(collect(&panicked, watch_list, |r| r.thread.clone())) {
+            return Poll::Ready(ts);
+        }
+        self.register_waker(watch_list, cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Like [`wait`], but returns a future instead of blocking the current thread.  The future
+    /// resolves as soon as one of the watched threads has panicked (which may be immediately).
+    ///
+    /// This method is only available with the `futures` feature.
+    ///
+    /// [`wait`]: #method.wait
+    #[cfg(feature = "futures")]
+    pub fn wait_async<'a>(&'a self, watch_list: &[ThreadId])
+        -> impl Future<Output = Vec<Thread>> + 'a
+    {
+        WaitFuture { monitor: self, watch_list: watch_list.to_vec(), waker: None }
+    }
+
+    /// Like [`wait_async`], but gives up when `timer` resolves, in which case the returned vector
+    /// is empty (as with [`wait_timeout`]).
+    ///
+    /// The monitor doesn't depend on any particular async runtime, so it can't make a timer
+    /// itself: pass in your runtime's sleep future, such as `tokio::time::sleep(dur)` or
+    /// `futures_timer::Delay::new(dur)`.  Unlike `wait_timeout`, this doesn't tie up a thread.
+    ///
+    /// This method is only available with the `futures` feature.
+    ///
+    /// [`wait_async`]: #method.wait_async
+    /// [`wait_timeout`]: #method.wait_timeout
+    #[cfg(feature = "futures")]
+    pub fn wait_timeout_async<'a, T>(&'a self, watch_list: &[ThreadId], timer: T)
+        -> impl Future<Output = Vec<Thread>> + 'a
+    where
+        T: Future<Output = ()> + 'a,
+    {
+        let wait = WaitFuture { monitor: self, watch_list: watch_list.to_vec(), waker: None };
+        TimeoutFuture { wait, timer: Box::pin(timer) }
+    }
+
+    /// Get a stream which yields a [`PanicRecord`] every time one of the watched threads panics.
+    ///
+    /// Unlike [`wait`], this is edge-triggered: panics which happened before the stream was
+    /// created are not yielded.  The stream never ends.
+    ///
+    /// This method is only available with the `futures` feature.
+    ///
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    /// [`wait`]: #method.wait
+    #[cfg(feature = "futures")]
+    pub fn stream(&self, watch_list: &[ThreadId]) -> impl Stream<Item = PanicRecord> {
+        let (tx, rx) = unbounded();
+        let sub = (watch_list.to_vec(), Arc::new(Subscriber::Stream(tx)));
+        lock(&self.subscribers).push(sub);
+        rx
+    }
+
+    /// Get a channel which receives a [`Thread`] every time one of the watched threads panics.
+    ///
+    /// Like [`stream`], this is edge-triggered: panics which happened before the channel was
+    /// created are not sent.  If you drop the receiver, the monitor will notice and forget about
+    /// it the next time one of the watched threads panics.
+    ///
+    /// [`Thread`]: https://doc.rust-lang.org/std/thread/struct.Thread.html
+    /// [`stream`]: #method.stream
+    pub fn subscribe<W: WatchList>(&self, watch_list: W) -> mpsc::Receiver<Thread> {
+        let (tx, rx) = mpsc::channel();
+        let sub = (watch_list.into_ids(), Arc::new(Subscriber::Channel(tx)));
+        lock(&self.subscribers).push(sub);
+        rx
+    }
+
+    /// Like [`subscribe`], but returns a [crossbeam channel], so that panics can be waited for
+    /// alongside other channels using crossbeam's `select!`.
+    ///
+    /// This method is only available with the `crossbeam` feature.
+    ///
+    /// [`subscribe`]: #method.subscribe
+    /// [crossbeam channel]: https://docs.rs/crossbeam-channel
+    #[cfg(feature = "crossbeam")]
+    pub fn subscribe_crossbeam<W: WatchList>(&self, watch_list: W)
+        -> crossbeam_channel::Receiver<Thread>
+    {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let sub = (watch_list.into_ids(), Arc::new(Subscriber::Crossbeam(tx)));
+        lock(&self.subscribers).push(sub);
+        rx
+    }
+
+    /// The number of subscriptions (made with [`subscribe`], [`subscribe_crossbeam`], or
+    /// [`stream`]) which the monitor is still holding on to.
+    ///
+    /// The monitor doesn't keep a receiver alive; it only holds the sending end of the channel.
+    /// When a receiver is dropped, the subscription is thrown away the next time one of its
+    /// watched threads panics (or, for a [`stream`], the next time any thread panics).  So a
+    /// dropped receiver costs a little memory until then, but no more.
+    ///
+    /// [`stream`]: #method.stream
+    /// [`subscribe`]: #method.subscribe
+    /// [`subscribe_crossbeam`]: #method.subscribe_crossbeam
+    pub fn subscriber_count(&self) -> usize {
+        lock(&self.subscribers).len()
+    }
+
+    /// Register a callback which is run every time a thread panics, before anyone waiting on the
+    /// thread is woken.  Callbacks are run in the order they were registered.
+    ///
+    /// This is for things which must happen *immediately*, like flushing a buffer or flipping a
+    /// health flag.  The callback runs inside the panic hook, on the panicking thread, so:
+    ///
+    ///  * it must not panic: a panic inside a panic hook aborts the process;
+    ///  * it may call the monitor's methods, but they won't know about this panic yet;
+    ///  * it should be quick, since it holds up the panicking thread (and, if several threads
+    ///    panic at once, the others too).
+    pub fn on_panic<F: Fn(&Thread) + Send + Sync + 'static>(&self, f: F) {
+        write(&self.callbacks).push(Arc::new(f));
+    }
+
+    /// Register a callback which is run every time a call to [`wait`], [`wait_timeout`], or one of
+    /// the other blocking waits (such as `wait_records`, `wait_first`, `wait_all`, or a
+    /// [`Cursor`]'s `wait`) returns some threads.  It's given the returned threads, and how long
+    /// the call took.  The non-blocking methods (`check`, `try_wait`, and so on, including a
+    /// `wait_deadline` whose deadline has already passed), the async and poll-style methods, and
+    /// subscriptions don't run them.  [`wait_any`] runs the callbacks of the monitor whose threads
+    /// it returns.
+    ///
+    /// This is for measuring how quickly supervisors notice panics, and the like.  Unlike
+    /// [`on_panic`] callbacks, which run once per panic on the panicking thread, these run once
+    /// per observation, on the waiting thread, after the monitor's locks have been released.  A
+    /// wait which times out doesn't run them.  When no callbacks are registered, they cost the
+    /// waits next to nothing.
+    ///
+    /// [`Cursor`]: struct.Cursor.html
+    /// [`on_panic`]: #method.on_panic
+    /// [`wait`]: #method.wait
+    /// [`wait_any`]: fn.wait_any.html
+    /// [`wait_timeout`]: #method.wait_timeout
+    pub fn on_wait<F: Fn(&[Thread], Duration) + Send + Sync + 'static>(&self, f: F) {
+        write(&self.wait_callbacks).push(Arc::new(f));
+    }
+
+    /// Set a function which sorts panics into categories, by looking at their payloads.  The code
+    /// it returns is stored in the [`PanicRecord`] (see [`PanicRecord::class`]), so a supervisor
+    /// can decide what to do about a crash without having to pick apart its message.  This
+    /// replaces any classifier which was set previously.
+    ///
+    /// The payload is only available from inside the panic hook, and isn't `Clone`, so this is
+    /// the only chance to look at it.  The same rules apply as for [`on_panic`]: the classifier
+    /// must not panic, and it should be quick.
+    ///
+    /// ```
+    /// # use panic_monitor::PanicMonitor;
+    /// # use std::thread;
+    /// # static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+    /// # PANIC_MONITOR.init();
+    /// struct Timeout;
+    /// PANIC_MONITOR.classify_payloads(|payload| {
+    ///     if payload.is::<Timeout>() { Some(1) } else { None }
+    /// });
+    ///
+    /// let h = thread::spawn(|| std::panic::panic_any(Timeout));
+    /// assert_eq!(PANIC_MONITOR.wait_records([&h])[0].class(), Some(1));
+    /// # h.join().unwrap_err();
+    /// ```
+    ///
+    /// [`on_panic`]: #method.on_panic
+    /// [`PanicRecord`]: struct.PanicRecord.html
+    /// [`PanicRecord::class`]: struct.PanicRecord.html#method.class
+    pub fn classify_payloads<F>(&self, f: F)
+    where
+        F: Fn(&(dyn Any + Send)) -> Option<u32> + Send + Sync + 'static,
+    {
+        *write(&self.classifier) = Some(Arc::new(f));
+    }
+
+    /// Get a [`Cursor`] over the watched threads.  Unlike [`wait`], the cursor's `wait` only
+    /// returns once a watched thread has panicked since the previous call, and it tells you which
+    /// of the returned threads are new.
+    ///
+    /// [`Cursor`]: struct.Cursor.html
+    /// [`wait`]: #method.wait
+    pub fn cursor<W: WatchList>(&self, watch_list: W) -> Cursor<'_> {
+        Cursor { monitor: self, watch_list: watch_list.into_ids(), seen: Generation::default() }
+    }
+
+    /// Block the current thread until one of the watched threads panics after `since`.  Returns
+    /// the threads which panicked after `since` (never empty), together with a `Generation` to
+    /// pass in next time.
+    ///
+    /// This is an edge-triggered version of [`wait`]: by threading the returned `Generation`
+    /// through successive calls, you're told about each panic exactly once, without needing to
+    /// [`forget`] anything.  Start with [`generation`] to ignore panics which have already
+    /// happened, or with `Generation::default()` to include them.
+    ///
+    /// [`forget`]: #method.forget
+    /// [`generation`]: #method.generation
+    /// [`wait`]: #method.wait
+    pub fn wait_new<W: WatchList>(&self, watch_list: W, since: Generation)
+        -> (Vec<Thread>, Generation)
+    {
+        let watch_list = &watch_list.into_ids();
+        let start = Instant::now();
+        let (threads, generation) = self.block_until(watch_list, |panicked| {
+            let threads = collect(panicked, watch_list, |r| (r.thread.clone(), r.seq))
+                .into_iter()
+                .filter(|&(_, seq)| seq >= since.0)
+                .map(|(t, _)| t)
+                .collect();
+            non_empty(threads).map(|ts| (ts, self.current_generation()))
+        });
+        self.observe(start, &threads);
+        (threads, generation)
+    }
+
+    /// The current `Generation`.  A [`wait_new`] with this value will only return threads which
+    /// panic from now on.
+    ///
+    /// [`wait_new`]: #method.wait_new
+    pub fn generation(&self) -> Generation {
+        let _panicked = read(&self.panicked);  // Make sure every numbered record has been stored
+        self.current_generation()
+    }
+
+    /// All the panics of the given thread which have been recorded, oldest first.
+    ///
+    /// A thread can panic multiple times if it uses [`catch_unwind`].  If the thread has never
+    /// panicked, the returned vector is empty.
+    ///
+    /// [`catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+    pub fn history(&self, tid: ThreadId) -> Vec<PanicRecord> {
+        let panicked = read(&self.panicked);
+        panicked.get(&tid).cloned().unwrap_or_default()
+    }
+
+    /// Forget that the given thread ever panicked.
+    ///
+    /// After calling this, [`wait`] will block on the thread again as if it had never panicked.
+    /// This is useful once you've dealt with a panic (eg. by respawning the worker), since it
+    /// frees the memory used by the thread's records.  The thread's [`thread_number`] is forgotten
+    /// too, so if it panics again it's given a new one.  [`panic_count`] is not affected.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`panic_count`]: #method.panic_count
+    /// [`thread_number`]: #method.thread_number
+    pub fn forget(&self, tid: ThreadId) {
+        write(&self.panicked).remove(&tid);
+        lock(&self.thread_numbers).remove(&tid);
+    }
+
+    /// Forget about the threads spawned with [`spawn`] or [`spawn_with`] which have since
+    /// finished, along with their panics and thread numbers.  Returns the number of threads whose
+    /// records were thrown away.
+    ///
+    /// This keeps memory usage bounded in processes which spawn lots of short-lived threads.  The
+    /// monitor can only tell whether a thread has finished if it spawned it, so the records of
+    /// other threads are left alone (use [`forget`] for those).  Once a thread has been pruned, it
+    /// no longer appears in [`spawned`].  Since thread IDs are never reused and a finished thread
+    /// can't panic again, a pruned ID never comes back: waiting on it blocks forever.
+    ///
+    /// [`forget`]: #method.forget
+    /// [`spawn`]: #method.spawn
+    /// [`spawn_with`]: #method.spawn_with
+    /// [`spawned`]: #method.spawned
+    pub fn prune(&self) -> usize {
+        let mut finished = vec![];
+        lock(&self.spawned).retain(|s| {
+            let is_alive = s.alive.strong_count() > 0;
+            if !is_alive { finished.push(s.tid); }
+            is_alive
+        });
+        let mut panicked = write(&self.panicked);
+        let mut counts = lock(&self.thread_panic_counts);
+        let mut numbers = lock(&self.thread_numbers);
+        for tid in &finished { counts.remove(tid); numbers.remove(tid); }
+        finished.iter().filter(|tid| panicked.remove(tid).is_some()).count()
+    }
+
+    /// Forget about all the panics which have been recorded so far.  See [`forget`].
+    ///
+    /// [`forget`]: #method.forget
+    pub fn clear(&self) {
+        write(&self.panicked).clear();
+    }
+
+    /// Put the monitor back in the state it was in before [`init`] was called: forget all the
+    /// panics and thread numbers, zero the counters, and [`uninstall`] the hook.  Thread numbers
+    /// start from zero again, and waiting on an uninitialised monitor warns again.
+    ///
+    /// This is meant for tests which share a global monitor, so that each one can start from a
+    /// clean slate by calling `reset` followed by `init`.  Some things are kept:
+    ///
+    ///  * settings, including the [payload classifier];
+    ///  * [`on_panic`] and [`on_wait`] callbacks, and subscribers;
+    ///  * the threads which the monitor spawned, so that [`spawned`], [`wait_done`], and
+    ///    [`join_all`] still know about them;
+    ///  * [`Generation`]s, which keep counting up, so tokens from before the reset remain valid.
+    ///
+    /// [payload classifier]: #method.classify_payloads
+    /// [`Generation`]: struct.Generation.html
+    /// [`init`]: #method.init
+    /// [`join_all`]: #method.join_all
+    /// [`on_panic`]: #method.on_panic
+    /// [`on_wait`]: #method.on_wait
+    /// [`spawned`]: #method.spawned
+    /// [`uninstall`]: #method.uninstall
+    /// [`wait_done`]: #method.wait_done
+    pub fn reset(&self) {
+        self.uninstall();
+        let mut panicked = write(&self.panicked);
+        panicked.clear();
+        self.panic_count.store(0, Ordering::Relaxed);
+        lock(&self.thread_panic_counts).clear();
+        let mut numbers = lock(&self.thread_numbers);
+        numbers.clear();
+        self.next_thread_number.store(0, Ordering::Relaxed);  // Numbers are assigned under the lock
+        drop(numbers);
+        self.warned_uninitialized.store(false, Ordering::Relaxed);
+        lock(&self.recent).clear();
+        self.wakeups.store(0, Ordering::Relaxed);
+        self.panic_wakeups.store(0, Ordering::Relaxed);
+        self.coalesced.store(0, Ordering::Relaxed);
+    }
+
+    /// Get the records of every panic which has been recorded, in the order they were recorded.
+    ///
+    /// This is a consistent point-in-time copy of everything the monitor knows.  With the `serde`
+    /// feature, it's ready to be serialized and shipped off to a crash-report collector.  It's
+    /// taken under a single lock acquisition, so panics which happen concurrently are either
+    /// entirely included or entirely absent.
+    ///
+    /// For example, the body of a debug endpoint might look like this:
+    ///
+    /// ```
+    /// # use panic_monitor::PanicMonitor;
+    /// # static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+    /// use std::fmt::Write;
+    ///
+    /// let mut body = String::new();
+    /// for r in PANIC_MONITOR.snapshot() {
+    ///     let name = r.thread().name().unwrap_or("<unnamed>");
+    ///     writeln!(body, "#{} {}: {}", r.thread_number(), name, r.message()).unwrap();
+    /// }
+    /// ```
+    pub fn snapshot(&self) -> Vec<PanicRecord> {
+        let panicked = read(&self.panicked);
+        let mut records: Vec<PanicRecord> =
+            panicked.values().flat_map(|rs| rs.iter().cloned()).collect();
+        records.sort_by_key(|r| r.seq);
+        records
+    }
+
+    /// Call `f` on every panic which has been recorded, without cloning anything.  This is the
+    /// allocation-free counterpart to [`snapshot`], for reporting on a large history.
+    ///
+    /// The records are visited thread by thread, oldest first within each thread, but the order
+    /// of the threads is arbitrary.  Like [`snapshot`], this sees a consistent point in time.
+    ///
+    /// `f` is called while the monitor is locked, which holds up panicking threads.  So it should
+    /// be quick, it must not panic, and it must not call back into the monitor, or it may
+    /// deadlock.
+    ///
+    /// [`snapshot`]: #method.snapshot
+    pub fn for_each<F: FnMut(&PanicRecord)>(&self, mut f: F) {
+        let panicked = read(&self.panicked);
+        for r in panicked.values().flatten() { f(r); }
+    }
+
+    /// The number which the monitor has given to the thread (see
+    /// [`PanicRecord::thread_number`]), or `None` if the monitor hasn't seen the thread yet.
+    ///
+    /// [`PanicRecord::thread_number`]: struct.PanicRecord.html#method.thread_number
+    pub fn thread_number(&self, tid: ThreadId) -> Option<u64> {
+        lock(&self.thread_numbers).get(&tid).cloned()
+    }
+
+    /// Tell the monitor about a thread which is already running, and return its [`thread_number`].
+    ///
+    /// The monitor normally numbers a thread the first time the thread panics (or when it's
+    /// spawned with [`spawn`]).  Registering a thread up front gives it a number straight away,
+    /// so it can be included in reports and the like even if it never panics.  Registering a
+    /// thread more than once just returns the same number.  This doesn't affect whether the
+    /// thread's panics are recorded: every thread's are, once the monitor is initialised.
+    ///
+    /// [`spawn`]: #method.spawn
+    /// [`thread_number`]: #method.thread_number
+    pub fn register_thread(&self, thread: &Thread) -> u64 {
+        self.assign_thread_number(thread.id())
+    }
+
+    /// The total number of panics which have been observed since the `PanicMonitor` was
+    /// initialised, across all threads (watched or not).
+    ///
+    /// This counts panic events: a thread which panics twice (by way of [`catch_unwind`]) is
+    /// counted twice.  See [`panicked_thread_count`] for the number of distinct threads.
+    ///
+    /// [`catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+    /// [`panicked_thread_count`]: #method.panicked_thread_count
+    pub fn panic_count(&self) -> usize {
+        self.panic_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of times the given thread has panicked since the `PanicMonitor` was
+    /// initialised.  Use [`history`] to see the records of each panic.
+    ///
+    /// Like [`panic_count`], this isn't affected by [`forget`], [`clear`], or the [`max_history`]
+    /// limit, so it can be more than the length of the history.  It's only reset by [`reset`], or
+    /// by [`prune`] once the thread has finished.  This makes it suitable for policies like
+    /// "restart a worker up to 3 times, then give up on it".
+    ///
+    /// [`clear`]: #method.clear
+    /// [`forget`]: #method.forget
+    /// [`history`]: #method.history
+    /// [`max_history`]: struct.PanicMonitorBuilder.html#method.max_history
+    /// [`panic_count`]: #method.panic_count
+    /// [`prune`]: #method.prune
+    /// [`reset`]: #method.reset
+    pub fn panic_count_for(&self, tid: ThreadId) -> usize {
+        lock(&self.thread_panic_counts).get(&tid).cloned().unwrap_or(0)
+    }
+
+    /// The number of distinct threads which have panicked since the `PanicMonitor` was
+    /// initialised.  This is the same as [`len`].
+    ///
+    /// [`len`]: #method.len
+    pub fn panicked_thread_count(&self) -> usize {
+        read(&self.panicked).len()
+    }
+
+    /// The number of panics which the monitor holds records for, grouped by the name of the thread
+    /// which panicked (`None` for unnamed threads).
+    ///
+    /// This is for reports like "the worker pool has had 12 crashes, and the I/O thread has had 1",
+    /// so it's most useful when threads with the same role share a name.  Only the records which
+    /// are still held are counted (see [`history`]).
+    ///
+    /// [`history`]: #method.history
+    pub fn count_by_name(&self) -> HashMap<Option<String>, usize> {
+        let panicked = read(&self.panicked);
+        let mut counts = HashMap::new();
+        for rs in panicked.values() {
+            // A thread's name never changes, so all of its records agree
+            let name = rs[0].thread.name().map(str::to_string);
+            *counts.entry(name).or_insert(0) += rs.len();
+        }
+        counts
+    }
+
+    /// The number of threads which the monitor holds records for.  This is cheap: nothing is
+    /// cloned.
+    ///
+    /// This counts distinct threads, not panics: a thread which has panicked several times counts
+    /// once (see [`panic_count`] for the number of panics).  Threads whose records have all been
+    /// thrown away, by [`forget`], [`prune`], or the [`max_history`] limit, aren't counted.
+    ///
+    /// [`forget`]: #method.forget
+    /// [`max_history`]: struct.PanicMonitorBuilder.html#method.max_history
+    /// [`panic_count`]: #method.panic_count
+    /// [`prune`]: #method.prune
+    pub fn len(&self) -> usize {
+        read(&self.panicked).len()
+    }
+
+    /// Is `len` zero?  In other words, has no thread panicked (or have all the records been thrown
+    /// away)?
+    pub fn is_empty(&self) -> bool {
+        read(&self.panicked).is_empty()
+    }
+
+    /// The number of threads which are currently blocked in one of the waiting methods, such as
+    /// [`wait`] or [`wait_timeout`].  A waiter which returned straight away (because a thread it
+    /// was watching had already panicked) was never blocked, so it isn't counted.  Futures,
+    /// streams, and subscriptions aren't counted either.
+    ///
+    /// [`wait`]: #method.wait
+    /// [`wait_timeout`]: #method.wait_timeout
+    pub fn waiter_count(&self) -> usize {
+        self.waiter_count.load(Ordering::Relaxed)
+    }
+
+    /// Statistics about how the monitor's waiters have been woken up.  See [`Stats`].
+    ///
+    /// [`Stats`]: struct.Stats.html
+    pub fn stats(&self) -> Stats {
+        // Load `panic_wakeups` first: every panic wakeup is counted in `wakeups` beforehand, so
+        // this way round the snapshot doesn't usually have more panic wakeups than wakeups
+        let panic_wakeups = self.panic_wakeups.load(Ordering::Relaxed);
+        Stats {
+            wakeups: self.wakeups.load(Ordering::Relaxed),
+            panic_wakeups,
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Make a record of the panicking thread and notify everyone who's waiting on it.  This is
+    /// called from inside the panic hook.  Returns true if the process should abort.
+    fn record(&self, info: &panic::PanicHookInfo) -> bool {
+        if recovering() { return false; }
+        let thread_number = self.assign_thread_number(thread::current().id());
+        let now = self.clock.now();
+        let mut record = PanicRecord::capture(info, thread_number, self.backtraces, now);
+        #[cfg(feature = "log")]
+        if self.log_panics { log_record(&record); }
+        #[cfg(feature = "tracing")]
+        if self.trace_panics { trace_record(&record); }
+        // Don't hold any of our locks while running user code: a callback which calls back into
+        // the monitor (even `on_panic`) would deadlock
+        let classifier = read(&self.classifier).clone();
+        record.class = classifier.and_then(|f| f(info.payload()));
+        let callbacks = read(&self.callbacks).clone();
+        for f in &callbacks { f(&record.thread); }
+        let record_tid = record.thread.id();
+        #[cfg(feature = "metrics")]
+        let thread_name = record.thread.name().unwrap_or("<unnamed>").to_string();
+        let tripped = self.trip(record.instant);
+        let mut panicked = write(&self.panicked);
+        record.seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        // Pick out the subscribers while `panicked` is locked, but only send to them once all our
+        // locks are released: sending to a stream wakes its task, which may poll on the spot
+        let due = interested(&mut lock(&self.subscribers), record_tid);
+        let published = if due.is_empty() { None } else { Some(record.clone()) };
+        panicked.entry(record.thread.id()).or_default().push(record);
+        if let Some(max) = self.max_history { evict(&mut panicked, max); }
+        self.panic_count.fetch_add(1, Ordering::Relaxed);
+        *lock(&self.thread_panic_counts).entry(record_tid).or_insert(0) += 1;
+        #[cfg(feature = "metrics")]
+        let thread_count = panicked.len();
+        // Store the record, *then* unlock, *then* wake the waiters.  Storing first means a waiter
+        // can't wake up and miss the record; unlocking before waking means it doesn't wake up
+        // only to block on `panicked` straight away.
+        drop(panicked);
+        if let Some(ref record) = published { self.publish(due, record); }
+        self.notify(record_tid);
+        self.wake(record_tid);
+        #[cfg(feature = "metrics")]
+        if self.record_metrics { report_metrics(&thread_name, thread_count); }
+        tripped
+    }
+
+    /// Send the record to the given subscribers, and forget about any which have gone away.
+    fn publish(&self, subscribers: Vec<Arc<Subscriber>>, record: &PanicRecord) {
+        let gone: Vec<_> = subscribers.into_iter().filter(|sub| !sub.send(record)).collect();
+        if gone.is_empty() { return; }
+        lock(&self.subscribers).retain(|(_, sub)| !gone.iter().any(|g| Arc::ptr_eq(g, sub)));
+    }
+
+    /// Note that a panic happened at `now`, and check whether the circuit breaker has tripped.
+    fn trip(&self, now: Instant) -> bool {
+        let (count, window) = match self.abort_after { Some(x) => x, None => return false };
+        let mut recent = lock(&self.recent);
+        recent.push_back(now);
+        while recent.front().is_some_and(|&t| now.duration_since(t) > window) {
+            recent.pop_front();
+        }
+        recent.len() >= count
+    }
+
+    /// The generation which the next record will belong to.  Only meaningful while holding a lock
+    /// on `panicked`, since records are numbered before they're stored.
+    fn current_generation(&self) -> Generation {
+        Generation(self.next_seq.load(Ordering::Relaxed))
+    }
+
+    /// Get the number of the given thread, assigning it a new one if necessary.
+    fn assign_thread_number(&self, tid: ThreadId) -> u64 {
+        *lock(&self.thread_numbers).entry(tid)
+            .or_insert_with(|| self.next_thread_number.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Wake all the tasks which are interested in the given thread, forgetting about any futures
+    /// which have been dropped and any one-shot wakers which have been used.
+    fn wake(&self, tid: ThreadId) {
+        let mut due = vec![];
+        lock(&self.wakers).retain(|(watch_list, entry)| {
+            match *entry {
+                #[cfg(feature = "futures")]
+                WakerEntry::Future(ref slot) => {
+                    let slot = match slot.upgrade() { Some(x) => x, None => return false };
+                    if watches(watch_list, tid) {
+                        let waker = lock(&slot).take();
+                        due.extend(waker);
+                    }
+                    true
+                }
+                WakerEntry::Once(ref waker) => {
+                    if !watches(watch_list, tid) { return true; }
+                    due.push(waker.clone());
+                    false
+                }
+            }
+        });
+        // Only now that everything is unlocked: a waker may poll its task on the spot, and the
+        // task may call back into the monitor
+        for waker in due { waker.wake(); }
+    }
+
+    /// Wake the blocked threads which are interested in the given thread.  Nobody else is
+    /// disturbed.
+    fn notify(&self, tid: ThreadId) {
+        let waiters = lock(&self.waiters);
+        let watching = waiters.by_thread.get(&tid).into_iter().flatten();
+        for signal in watching.chain(&waiters.any) {
+            if !signal.fire() { self.coalesced.fetch_add(1, Ordering::Relaxed); }
+        }
+    }
+
+    /// Register a signal which will be fired whenever one of the threads in `interest` panics (or
+    /// whenever any thread panics, if `interest` is empty).  The signal is unregistered when the
+    /// returned guard is dropped.
+    fn register<'a>(&'a self, interest: &'a [ThreadId], signal: Arc<Signal>) -> Registration<'a> {
+        let mut waiters = lock(&self.waiters);
+        if interest.is_empty() {
+            waiters.any.push(signal.clone());
+        } else {
+            for tid in interest {
+                waiters.by_thread.entry(*tid).or_default().push(signal.clone());
+            }
+        }
+        self.waiter_count.fetch_add(1, Ordering::Relaxed);
+        Registration { monitor: self, interest, signal }
+    }
+
+    fn wait_with<T, F: Fn(&PanicRecord) -> T>(&self, watch_list: &[ThreadId], f: F) -> Vec<T> {
+        if read(&self.wait_callbacks).is_empty() {
+            return self.block_until(watch_list, |panicked| {
+                non_empty(collect(panicked, watch_list, &f))
+            });
+        }
+        let start = Instant::now();
+        let xs = self.block_until(watch_list, |panicked| {
+            non_empty(collect(panicked, watch_list, |r| (r.thread.clone(), f(r))))
+        });
+        self.observed(xs, start)
+    }
+
+    fn wait_timeout_with<T, F>(&self, watch_list: &[ThreadId], dur: Duration, f: F) -> Vec<T>
+    where
+        F: Fn(&PanicRecord) -> T,
+    {
+        if read(&self.wait_callbacks).is_empty() {
+            return self.block_until_timeout(watch_list, dur, |panicked| {
+                non_empty(collect(panicked, watch_list, &f))
+            })
+                .unwrap_or_default();
+        }
+        let start = Instant::now();
+        let xs = self.block_until_timeout(watch_list, dur, |panicked| {
+            non_empty(collect(panicked, watch_list, |r| (r.thread.clone(), f(r))))
+        });
+        xs.map(|xs| self.observed(xs, start)).unwrap_or_default()
+    }
+
+    /// Tell the `on_wait` callbacks about the threads which a wait is about to return.
+    fn observed<T>(&self, xs: Vec<(Thread, T)>, start: Instant) -> Vec<T> {
+        let (threads, xs): (Vec<Thread>, Vec<T>) = xs.into_iter().unzip();
+        self.observe(start, &threads);
+        xs
+    }
+
+    /// Tell the `on_wait` callbacks about the threads which a wait which started at `start` is
+    /// about to return.  Nothing is observed if no threads are returned (ie. the wait gave up).
+    fn observe<'t, I: IntoIterator<Item = &'t Thread>>(&self, start: Instant, threads: I) {
+        let callbacks = read(&self.wait_callbacks).clone();
+        if callbacks.is_empty() { return; }
+        let threads: Vec<Thread> = threads.into_iter().cloned().collect();
+        if threads.is_empty() { return; }
+        let elapsed = start.elapsed();
+        for f in &callbacks { f(&threads, elapsed); }
+    }
+
+    fn check_with<T, F: Fn(&PanicRecord) -> T>(&self, watch_list: &[ThreadId], f: F) -> Vec<T> {
+        let panicked = read(&self.panicked);
+        collect(&panicked, watch_list, &f)
+    }
+
+    /// Block the current thread until `poll` returns something.  `poll` is re-evaluated every
+    /// time one of the threads in `interest` panics (or any thread, if `interest` is empty).
+    fn block_until<T, F>(&self, interest: &[ThreadId], poll: F) -> T
+    where
+        F: FnMut(&Panicked) -> Option<T>,
+    {
+        // Without a deadline or a token, `block` never gives up
+        self.block(interest, None, None, poll).unwrap()
+    }
+
+    /// Like `block_until`, but gives up and returns `None` when the timeout expires.
+    fn block_until_timeout<T, F>(&self, interest: &[ThreadId], dur: Duration, poll: F) -> Option<T>
+    where
+        F: FnMut(&Panicked) -> Option<T>,
+    {
+        // If the addition overflows, the timeout is effectively infinite
+        self.block(interest, Instant::now().checked_add(dur), None, poll)
+    }
+
+    /// Like `block_until`, but gives up and returns `None` once the deadline has passed.  `poll`
+    /// is always evaluated at least once.
+    fn block_until_deadline<T, F>(&self, interest: &[ThreadId], deadline: Instant, poll: F)
+        -> Option<T>
+    where
+        F: FnMut(&Panicked) -> Option<T>,
+    {
+        self.block(interest, Some(deadline), None, poll)
+    }
+
+    /// Block until `poll` returns something, or the deadline passes, or the token is cancelled.
+    /// Returns `None` in the latter two cases.
+    fn block<T, F>(
+        &self,
+        interest: &[ThreadId],
+        deadline: Option<Instant>,
+        cancel: Option<&CancelToken>,
+        mut poll: F,
+    ) -> Option<T>
+    where
+        F: FnMut(&Panicked) -> Option<T>,
+    {
+        let cancelled = || cancel.is_some_and(CancelToken::is_cancelled);
+        // Fast path: don't bother registering if we can answer straight away
+        if let Some(x) = poll(&read(&self.panicked)) { return Some(x); }
+        if cancelled() { return None; }
+        if deadline.is_none() { self.warn_if_uninitialized(); }
+        // Register *before* polling again: if a thread panics (or the token is cancelled) after
+        // we've polled, our signal will already have been fired by the time we go to sleep, so we
+        // won't miss it.
+        let registration = self.register(interest, Arc::new(Signal::new()));
+        let _cancel_registration = cancel.map(|c| c.register(&registration.signal));
+        let mut woken = false;
+        loop {
+            if let Some(x) = poll(&read(&self.panicked)) {
+                if woken { self.panic_wakeups.fetch_add(1, Ordering::Relaxed); }
+                return Some(x);
+            }
+            if cancelled() { return None; }
+            if !registration.signal.wait(deadline) { return None; }
+            self.wakeups.fetch_add(1, Ordering::Relaxed);
+            woken = true;
+        }
+    }
+}
+
+impl Default for PanicMonitor {
+    fn default() -> PanicMonitor {
+        PanicMonitor::new()
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<String>> = const { RefCell::new(None) };
+    static RECOVERING: Cell<usize> = const { Cell::new(0) };  // Depth of nested `catch_unwind`s
+    static UNWINDING: Cell<bool> = const { Cell::new(false) };  // Is a recorded panic unwinding?
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };  // Is one of our hooks running?
+}
+
+/// Like [`std::panic::catch_unwind`], but panics which it catches aren't recorded by any monitor.
+///
+/// Use this if a thread recovers from panics and keeps running: otherwise, its supervisor would
+/// think it had died.  The panics are still passed on to the previously-installed hook, so they're
+/// printed as usual.  Panics which escape `f` some other way (eg. because it spawns a thread) are
+/// unaffected.
+///
+/// [`std::panic::catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+pub fn catch_unwind<F: FnOnce() -> R + panic::UnwindSafe, R>(f: F) -> thread::Result<R> {
+    struct Recovering;
+    impl Drop for Recovering {
+        fn drop(&mut self) { RECOVERING.with(|r| r.set(r.get() - 1)); }
+    }
+    RECOVERING.with(|r| r.set(r.get() + 1));
+    let _recovering = Recovering;
+    panic::catch_unwind(f)
+}
+
+/// Is the current thread inside a `catch_unwind`?  Called from inside the hook.
+fn recovering() -> bool {
+    RECOVERING.try_with(|r| r.get() > 0).unwrap_or(false)
+}
+
+/// Has the current thread already panicked, as far as we know?  Called from inside the hook.
+fn unwinding() -> bool {
+    UNWINDING.try_with(Cell::get).unwrap_or(false)
+}
+
+/// Forget that the current thread was unwinding, if it's stopped.  std doesn't tell us when a panic
+/// is caught, so this is called from the functions which a thread is likely to call afterwards.
+fn note_unwind_ended() {
+    if !thread::panicking() { let _ = UNWINDING.try_with(|u| u.set(false)); }
+}
+
+/// Was the backtrace captured inside a `Drop` impl (eg. because the thread is unwinding)?
+fn in_drop(backtrace: &str) -> bool {
+    backtrace.contains("drop_in_place")
+}
+
+/// Attach some context to the current thread, such as a job number or a shard, which will be
+/// included in the [`PanicRecord`] if the thread panics.  This replaces any context which was set
+/// previously.
+///
+/// This lets a crash report say "shard 7 panicked" rather than giving an opaque `ThreadId`.  The
+/// context applies to all monitors.
+///
+/// [`PanicRecord`]: struct.PanicRecord.html
+pub fn set_context<S: Into<String>>(context: S) {
+    let context = context.into();
+    note_unwind_ended();
+    CONTEXT.with(|c| *c.borrow_mut() = Some(context));
+}
+
+/// Remove the current thread's context.  See [`set_context`].
+///
+/// [`set_context`]: fn.set_context.html
+pub fn clear_context() {
+    note_unwind_ended();
+    CONTEXT.with(|c| *c.borrow_mut() = None);
+}
+
+/// The current thread's context.  This is called from inside the hook, so it mustn't panic: the
+/// thread-local may already have been destroyed, or (in theory) be borrowed.
+fn current_context() -> Option<String> {
+    CONTEXT.try_with(|c| c.try_borrow().ok().and_then(|c| c.clone())).ok().flatten()
+}
+
+/// The global monitor, which is used by the free functions [`init`], [`wait`], and [`check`].  It
+/// has the default settings, and it's initialised (see [`PanicMonitor::init`]) the first time
+/// it's used, so call [`init`] early on to make sure that no panics are missed.  After
+/// `global().uninstall()` (or `reset()`), the hook stays removed until [`init`] is called.
+///
+/// [`check`]: fn.check.html
+/// [`init`]: fn.init.html
+/// [`PanicMonitor::init`]: struct.PanicMonitor.html#method.init
+/// [`wait`]: fn.wait.html
+pub fn global() -> &'static PanicMonitor {
+    static GLOBAL: OnceLock<PanicMonitor> = OnceLock::new();
+    static INIT: Once = Once::new();
+    let monitor = GLOBAL.get_or_init(PanicMonitor::new);
+    // The hook can't be installed while panicking: leave it to the next use
+    if !thread::panicking() { INIT.call_once(|| monitor.init()); }
+    monitor
+}
+
+/// Initialise the [`global`] monitor.  Call this as early as you can, as with
+/// [`PanicMonitor::init`].  This reinstalls the hook if it's been removed with
+/// `global().uninstall()` or `global().reset()`.
+///
+/// [`global`]: fn.global.html
+/// [`PanicMonitor::init`]: struct.PanicMonitor.html#method.init
+pub fn init() {
+    global().init();
+}
+
+/// Block the current thread until one of the watched threads panics, using the [`global`]
+/// monitor.  See [`PanicMonitor::wait`].
+///
+/// [`global`]: fn.global.html
+/// [`PanicMonitor::wait`]: struct.PanicMonitor.html#method.wait
+pub fn wait<W: WatchList>(watch_list: W) -> Vec<Thread> {
+    global().wait(watch_list)
+}
+
+/// Check if any of the watched threads have panicked, using the [`global`] monitor.  See
+/// [`PanicMonitor::check`].
+///
+/// [`global`]: fn.global.html
+/// [`PanicMonitor::check`]: struct.PanicMonitor.html#method.check
+pub fn check<W: WatchList>(watch_list: W) -> Vec<Thread> {
+    global().check(watch_list)
+}
+
+/// Block the current thread until one of the watched threads of any of the given monitors panics.
+/// Returns the index of the monitor, and the panicked threads of its watch list (never empty).
+///
+/// Each monitor is paired with its own watch list, which is interpreted as in
+/// [`PanicMonitor::wait`] (so an empty watch list watches all of that monitor's threads).  If
+/// several monitors have panicked threads, the first one in the slice wins.
+///
+/// No helper threads are involved: the current thread registers a single wakeup with each of the
+/// monitors, so the cost is one registration per monitor per call, plus a [`check`] of every
+/// monitor whenever one of them wakes us up.
+///
+/// # Panics
+///
+/// Panics if `monitors` is empty, since it would block forever.
+///
+/// [`PanicMonitor::wait`]: struct.PanicMonitor.html#method.wait
+/// [`check`]: struct.PanicMonitor.html#method.check
+pub fn wait_any(monitors: &[(&PanicMonitor, &[ThreadId])]) -> (usize, Vec<Thread>) {
+    assert!(!monitors.is_empty(), "wait_any: no monitors to wait on");
+    let start = Instant::now();
+    let found = |(i, threads): (usize, Vec<Thread>)| {
+        monitors[i].0.observe(start, &threads);
+        (i, threads)
+    };
+    let poll = || {
+        monitors.iter().enumerate().find_map(|(i, &(monitor, watch_list))| {
+            non_empty(monitor.check(watch_list)).map(|ts| (i, ts))
+        })
+    };
+    if let Some(x) = poll() { return found(x); }
+    // As in `block`: register before polling again, so we can't miss a panic
+    let signal = Arc::new(Signal::new());
+    let _registrations: Vec<Registration> = monitors.iter()
+        .map(|&(monitor, watch_list)| monitor.register(watch_list, signal.clone()))
+        .collect();
+    loop {
+        if let Some(x) = poll() { return found(x); }
+        signal.wait(None);
+    }
+}
+
+/// Statistics about the wakeups of threads blocked in a `PanicMonitor`'s `wait` methods, as
+/// returned by [`PanicMonitor::stats`].
+///
+/// Only waiters which actually go to sleep are counted: a `wait` which returns straight away
+/// doesn't count as a wakeup.  A waiter is only woken when a thread it's watching panics (or its
+/// [`CancelToken`] is cancelled), but it may find that the panic isn't what it's waiting for: for
+/// instance, [`wait_all`] is woken by each of its threads in turn.  Threads blocked in the free
+/// function [`wait_any`] aren't counted, since it can't tell which of its monitors woke it.
+///
+/// [`PanicMonitor::stats`]: struct.PanicMonitor.html#method.stats
+/// [`CancelToken`]: struct.CancelToken.html
+/// [`wait_all`]: struct.PanicMonitor.html#method.wait_all
+/// [`wait_any`]: fn.wait_any.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    wakeups: u64,
+    panic_wakeups: u64,
+    coalesced: u64,
+}
+
+impl Stats {
+    /// The number of times a blocked waiter has been woken up.
+    pub fn wakeups(&self) -> u64 {
+        self.wakeups
+    }
+
+    /// The number of wakeups after which the waiter returned a panic.
+    pub fn panic_wakeups(&self) -> u64 {
+        self.panic_wakeups
+    }
+
+    /// The number of wakeups after which the waiter found nothing and went back to sleep (or gave
+    /// up).
+    pub fn spurious_wakeups(&self) -> u64 {
+        // The counters are read one at a time, so a snapshot may have more panic wakeups
+        self.wakeups.saturating_sub(self.panic_wakeups)
+    }
+
+    /// The number of times a waiter would have been woken, but wasn't, because it was already
+    /// due to wake up.
+    ///
+    /// When several watched threads panic in quick succession (a crash storm), the waiter is only
+    /// woken once for the whole burst, as long as it hasn't got round to running in between: it
+    /// looks at all the panics when it does.  Without this, it would be woken once per panic, and
+    /// all but the first wakeup would be spurious.
+    pub fn coalesced_notifications(&self) -> u64 {
+        self.coalesced
+    }
+}
+
+/// A point in the sequence of panics recorded by a `PanicMonitor`.  See
+/// [`PanicMonitor::wait_new`].
+///
+/// Generations are ordered: a later generation compares greater.  The default generation comes
+/// before every panic.  `Generation`s from different monitors can't be meaningfully compared.
+///
+/// [`PanicMonitor::wait_new`]: struct.PanicMonitor.html#method.wait_new
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Generation(u64);  // Sequence number of the first record which belongs to it
+
+/// Remembers which panics have already been reported, so that repeated `wait`s don't re-report
+/// them.  Created by [`PanicMonitor::cursor`].
+///
+/// A fresh cursor hasn't seen anything, so its first `wait` behaves like [`PanicMonitor::wait`]
+/// (with every returned thread marked as new).  If a thread panics again (by way of
+/// [`catch_unwind`]), it's reported as new again.
+///
+/// [`PanicMonitor::cursor`]: struct.PanicMonitor.html#method.cursor
+/// [`PanicMonitor::wait`]: struct.PanicMonitor.html#method.wait
+/// [`catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+pub struct Cursor<'a> {
+    monitor: &'a PanicMonitor,
+    watch_list: Vec<ThreadId>,
+    seen: Generation,  // Records older than this have already been reported
+}
+
+impl<'a> Cursor<'a> {
+    /// Block the current thread until one of the watched threads panics for the first time since
+    /// the last call.  Returns every watched thread which has panicked, each paired with a flag
+    /// saying whether it's new since the last call.  At least one of them is new.
+    pub fn wait(&mut self) -> Vec<(Thread, bool)> {
+        let Cursor { monitor, ref watch_list, ref mut seen } = *self;
+        let start = Instant::now();
+        let threads = monitor.block_until(watch_list, |panicked| {
+            advance(monitor, panicked, watch_list, seen)
+        });
+        monitor.observe(start, threads.iter().map(|(t, _)| t));
+        threads
+    }
+
+    /// Like [`wait`], but gives up when the timeout expires.  The returned vector is empty if and
+    /// only if the timeout expired, in which case nothing is marked as seen.
+    ///
+    /// [`wait`]: #method.wait
+    pub fn wait_timeout(&mut self, dur: Duration) -> Vec<(Thread, bool)> {
+        let Cursor { monitor, ref watch_list, ref mut seen } = *self;
+        let start = Instant::now();
+        let threads = monitor.block_until_timeout(watch_list, dur, |panicked| {
+            advance(monitor, panicked, watch_list, seen)
+        }).unwrap_or_default();
+        monitor.observe(start, threads.iter().map(|(t, _)| t));
+        threads
+    }
+}
+
+/// If any of the watched threads has a record the cursor hasn't seen, mark everything as seen
+/// and return the watched threads along with their "new" flags.  Must be called with the read
+/// lock held, so that `next_seq` agrees with `panicked`.
+fn advance(
+    monitor: &PanicMonitor,
+    panicked: &Panicked,
+    watch_list: &[ThreadId],
+    seen: &mut Generation,
+) -> Option<Vec<(Thread, bool)>> {
+    let threads = collect(panicked, watch_list, |r| (r.thread.clone(), r.seq >= seen.0));
+    if !threads.iter().any(|&(_, new)| new) { return None; }
+    *seen = monitor.current_generation();
+    Some(threads)
+}
+
+/// How a thread ended, as reported by [`PanicMonitor::wait_done`] and [`PanicMonitor::join_all`].
+///
+/// [`PanicMonitor::join_all`]: struct.PanicMonitor.html#method.join_all
+/// [`PanicMonitor::wait_done`]: struct.PanicMonitor.html#method.wait_done
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    /// The thread panicked.
+    Panicked(Thread),
+    /// The thread returned normally.
+    Finished,
+}
+
+/// A thread spawned by `PanicMonitor::spawn`.
+struct Spawned {
+    tid: ThreadId,
+    alive: Weak<()>,         // Dead once the thread has finished, one way or another
+    finished: CancelToken,   // Cancelled if the thread returns normally
+}
+
+/// The threads which are blocked in one of the `wait` methods.  Each has its own `Signal`, so a
+/// panic only wakes the waiters which are watching the thread that panicked.
+struct Waiters {
+    by_thread: Map<ThreadId, Vec<Arc<Signal>>>,  // Waiters watching specific threads
+    any: Vec<Arc<Signal>>,                       // Waiters watching every thread
+}
+
+/// A token which can be used to interrupt a [`wait_cancellable`] from another thread.
+///
+/// Clones of a token share their state: cancelling one cancels them all.  Once a token has been
+/// cancelled, it stays cancelled.
+///
+/// [`wait_cancellable`]: struct.PanicMonitor.html#method.wait_cancellable
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelInner>,
+}
+
+#[derive(Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    signals: Mutex<Vec<Arc<Signal>>>,  // The threads which are currently waiting on this token
+}
+
+impl CancelToken {
+    /// Create a new token, which hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancel the token, waking every thread which is waiting on it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        for signal in lock(&self.inner.signals).iter() { signal.fire(); }
+    }
+
+    /// Has the token been cancelled?
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fire `signal` when the token is cancelled, until the returned guard is dropped.
+    fn register(&self, signal: &Arc<Signal>) -> CancelRegistration<'_> {
+        lock(&self.inner.signals).push(signal.clone());
+        CancelRegistration { token: self, signal: signal.clone() }
+    }
+}
+
+/// Keeps a `Signal` registered with a `CancelToken`; unregisters it on drop.
+struct CancelRegistration<'a> {
+    token: &'a CancelToken,
+    signal: Arc<Signal>,
+}
+
+impl<'a> Drop for CancelRegistration<'a> {
+    fn drop(&mut self) {
+        lock(&self.token.inner.signals).retain(|s| !Arc::ptr_eq(s, &self.signal));
+    }
+}
+
+/// A one-shot wakeup for a single blocked thread.
+struct Signal {
+    fired: Mutex<bool>,
+    cvar: Condvar,
+}
+
+impl Signal {
+    fn new() -> Signal {
+        Signal { fired: Mutex::new(false), cvar: Condvar::new() }
+    }
+
+    /// Wake the waiter.  Returns false if it was already going to wake up: in that case, it hasn't
+    /// looked at the monitor since the last time the signal was fired, so it'll see whatever this
+    /// notification was for anyway.
+    fn fire(&self) -> bool {
+        let mut fired = lock(&self.fired);
+        if *fired { return false; }
+        *fired = true;
+        drop(fired);  // Release the lock before the waiter is woken, so it doesn't block on it
+        self.cvar.notify_one();
+        true
+    }
+
+    /// Block until the signal has been fired, and reset it.  Returns false if the deadline passed
+    /// first.
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut fired = lock(&self.fired);
+        while !*fired {
+            match deadline {
+                None => fired = sync::wait(&self.cvar, fired),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline { return false; }
+                    fired = sync::wait_timeout(&self.cvar, fired, deadline - now);
+                }
+            }
+        }
+        *fired = false;
+        true
+    }
+}
+
+/// Keeps a `Signal` registered with its monitor; unregisters it on drop.
+struct Registration<'a> {
+    monitor: &'a PanicMonitor,
+    interest: &'a [ThreadId],
+    signal: Arc<Signal>,
+}
+
+impl<'a> Drop for Registration<'a> {
+    fn drop(&mut self) {
+        self.monitor.waiter_count.fetch_sub(1, Ordering::Relaxed);
+        let mut waiters = lock(&self.monitor.waiters);
+        let signal = &self.signal;
+        if self.interest.is_empty() {
+            waiters.any.retain(|s| !Arc::ptr_eq(s, signal));
+        }
+        for tid in self.interest {
+            let now_empty = match waiters.by_thread.get_mut(tid) {
+                Some(signals) => { signals.retain(|s| !Arc::ptr_eq(s, signal)); signals.is_empty() }
+                None => false,
+            };
+            if now_empty { waiters.by_thread.remove(tid); }
+        }
+    }
+}
+
+/// The subscribers which are interested in the given thread.  Forgets about any others which are
+/// known to have gone away.
+fn interested(subscribers: &mut Vec<(Vec<ThreadId>, Arc<Subscriber>)>, tid: ThreadId)
+    -> Vec<Arc<Subscriber>>
+{
+    let mut due = vec![];
+    subscribers.retain(|(watch_list, sub)| {
+        if watches(watch_list, tid) { due.push(sub.clone()); true } else { sub.is_alive() }
+    });
+    due
+}
+
+/// Somewhere to send notifications of panics.
+enum Subscriber {
+    Channel(mpsc::Sender<Thread>),
+    #[cfg(feature = "futures")]
+    Stream(UnboundedSender<PanicRecord>),
+    #[cfg(feature = "crossbeam")]
+    Crossbeam(crossbeam_channel::Sender<Thread>),
+}
+
+impl Subscriber {
+    /// Returns false if the receiving end has gone away.
+    fn send(&self, record: &PanicRecord) -> bool {
+        match *self {
+            Subscriber::Channel(ref tx) => tx.send(record.thread.clone()).is_ok(),
+            #[cfg(feature = "futures")]
+            Subscriber::Stream(ref tx) => tx.unbounded_send(record.clone()).is_ok(),
+            #[cfg(feature = "crossbeam")]
+            Subscriber::Crossbeam(ref tx) => tx.send(record.thread.clone()).is_ok(),
+        }
+    }
+
+    /// Returns false if we know that the receiving end has gone away.  (std's channels don't tell
+    /// us this until we try to send something.)
+    fn is_alive(&self) -> bool {
+        match *self {
+            Subscriber::Channel(_) => true,
+            #[cfg(feature = "futures")]
+            Subscriber::Stream(ref tx) => !tx.is_closed(),
+            #[cfg(feature = "crossbeam")]
+            Subscriber::Crossbeam(_) => true,
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+type WakerSlot = Mutex<Option<Waker>>;
+
+/// A task which wants to be woken when a thread panics.
+enum WakerEntry {
+    #[cfg(feature = "futures")]
+    Future(Weak<WakerSlot>),  // A `WaitFuture`, which re-registers its waker on every poll
+    Once(Waker),              // From `register_waker`; forgotten once it's been woken
+}
+
+#[cfg(feature = "futures")]
+struct WaitFuture<'a> {
+    monitor: &'a PanicMonitor,
+    watch_list: Vec<ThreadId>,
+    waker: Option<Arc<WakerSlot>>,   // Registered with the monitor on first poll
+}
+
+#[cfg(feature = "futures")]
+impl<'a> Future for WaitFuture<'a> {
+    type Output = Vec<Thread>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<Thread>> {
+        let this = &mut *self;
+        // Hold the lock while registering the waker, so we can't miss a panic
+        let panicked = read(&this.monitor.panicked);
+        if let Some(ts) = non_empty(collect(&panicked, &this.watch_list, |r| r.thread.clone())) {
+            return Poll::Ready(ts);
+        }
+        match this.waker {
+            Some(ref slot) => *lock(slot) = Some(cx.waker().clone()),
+            None => {
+                let slot = Arc::new(Mutex::new(Some(cx.waker().clone())));
+                let entry = (this.watch_list.clone(), WakerEntry::Future(Arc::downgrade(&slot)));
+                lock(&this.monitor.wakers).push(entry);
+                this.waker = Some(slot);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "futures")]
+struct TimeoutFuture<'a, T> {
+    wait: WaitFuture<'a>,
+    timer: Pin<Box<T>>,
+}
+
+#[cfg(feature = "futures")]
+impl<'a, T: Future<Output = ()>> Future for TimeoutFuture<'a, T> {
+    type Output = Vec<Thread>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<Thread>> {
+        let this = &mut *self;
+        // Check for panics first, so a panic which has already happened wins over an expired timer
+        if let Poll::Ready(ts) = Pin::new(&mut this.wait).poll(cx) { return Poll::Ready(ts); }
+        this.timer.as_mut().poll(cx).map(|()| Vec::new())
+    }
+}
+
+/// Every panic which has been recorded, grouped by thread, in the order they happened.  The
+/// vectors are never empty.
+type Panicked = Map<ThreadId, Vec<PanicRecord>>;
+
+/// A `HashMap` which can be created in a `const fn`.  We don't need `RandomState`'s protection
+/// against HashDoS, since our keys are `ThreadId`s.
+type Map<K, V> = HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+
+const fn new_map<K, V>() -> Map<K, V> {
+    HashMap::with_hasher(BuildHasherDefault::new())
+}
+
+/// Does the watch list include the given thread?  (An empty watch list includes everything.)
+fn watches(watch_list: &[ThreadId], tid: ThreadId) -> bool {
+    watch_list.is_empty() || watch_list.contains(&tid)
+}
+
+/// Apply `f` to the most recent records of all the threads on the watch list which have panicked,
+/// in watch-list order.  Threads which appear in the watch list more than once are only included
+/// once, at their first position.
+///
+/// If the watch list is empty, every thread which has panicked is included, in the order of their
+/// most recent panics.
+fn collect<T, F>(panicked: &Panicked, watch_list: &[ThreadId], f: F) -> Vec<T>
+where
+    F: Fn(&PanicRecord) -> T,
+{
+    if watch_list.is_empty() { return collect_matching(panicked, |_| true, f); }
+    let mut watched_panicked = Vec::new();
+    collect_into(panicked, watch_list, f, &mut watched_panicked);
+    watched_panicked
+}
+
+/// Like `collect`, but appends to `out`.
+fn collect_into<T, F>(panicked: &Panicked, watch_list: &[ThreadId], f: F, out: &mut Vec<T>)
+where
+    F: Fn(&PanicRecord) -> T,
+{
+    if watch_list.is_empty() {
+        out.extend(collect_matching(panicked, |_| true, f));
+        return;
+    }
+    // Each watched thread contributes at most one element, and only if it's panicked.  Sizing
+    // the vector up front saves reallocating as it grows when lots of threads have died.
+    out.reserve(watch_list.len().min(panicked.len()));
+    for (i, tid) in watch_list.iter().enumerate() {
+        if watch_list[..i].contains(tid) { continue; }  // Only report each thread once
+        if let Some(r) = panicked.get(tid).and_then(|rs| rs.last()) {
+            out.push(f(r));
+        }
+    }
+}
+
+/// The most recent record of the first thread on the watch list which has panicked.  If the watch
+/// list is empty, the earliest of all the threads' most recent records.
+fn first<'a>(panicked: &'a Panicked, watch_list: &[ThreadId]) -> Option<&'a PanicRecord> {
+    if watch_list.is_empty() {
+        return panicked.values().filter_map(|rs| rs.last()).min_by_key(|r| r.seq);
+    }
+    watch_list.iter().find_map(|tid| panicked.get(tid).and_then(|rs| rs.last()))
+}
+
+/// Apply `f` to the most recent records of all the threads which have panicked and which satisfy
+/// `pred`, in the order of their most recent panics.
+fn collect_matching<T, P, F>(panicked: &Panicked, pred: P, f: F) -> Vec<T>
+where
+    P: Fn(&Thread) -> bool,
+    F: Fn(&PanicRecord) -> T,
+{
+    let mut latest: Vec<&PanicRecord> = panicked.values()
+        .filter_map(|rs| rs.last())
+        .filter(|r| pred(&r.thread))
+        .collect();
+    latest.sort_by_key(|r| r.seq);
+    latest.into_iter().map(f).collect()
+}
+
+/// How the panic hook refers to its `PanicMonitor`.
+#[derive(Clone)]
+enum HookTarget {
+    Static(&'static PanicMonitor),
+    Weak(Weak<PanicMonitor>),
+}
+
+/// A callback registered with `on_panic`.
+type Callback = Arc<dyn Fn(&Thread) + Send + Sync>;
+
+/// A callback registered with `on_wait`.
+type WaitCallback = Arc<dyn Fn(&[Thread], Duration) + Send + Sync>;
+
+/// A function registered with `classify_payloads`.
+type Classifier = Arc<dyn Fn(&(dyn Any + Send)) -> Option<u32> + Send + Sync>;
+
+/// A panic hook, as returned by `panic::take_hook`, but shareable.
+type Hook = dyn Fn(&panic::PanicHookInfo) + Sync + Send;
+
+/// What a monitor remembers about its hook, so that it can be reinstalled or uninstalled.
+struct Installed {
+    target: HookTarget,
+    live: Arc<AtomicBool>,  // Cleared on uninstall, so stray copies of the hook stop recording
+    previous: Arc<Hook>,    // The hook which was in place before `init`
+    record_first: bool,     // Whether to record panics before calling `previous`, or after
+}
+
+/// Install a panic hook which records panics in the target monitor (as long as `live` is set)
+/// and, if `chain` is set, calls the previously-installed hook (which is returned), either after
+/// recording or before it.
+fn install_hook(target: HookTarget, live: Arc<AtomicBool>, chain: bool, record_first: bool)
+    -> Arc<Hook>
+{
+    let hook: Arc<Hook> = Arc::from(panic::take_hook());
+    let previous = hook.clone();
+    panic::set_hook(Box::new(move|x| {
+        if !live.load(Ordering::SeqCst) { return hook(x); }  // Uninstalled: just pass it on
+        // If several monitors' hooks are chained, only the outermost one marks the thread as
+        // unwinding, so that the others don't mistake this panic for a double panic
+        let outermost = !IN_HOOK.try_with(|h| h.replace(true)).unwrap_or(true);
+        if chain && !record_first { hook(x); }
+        let abort = match target {
+            HookTarget::Static(monitor) => monitor.record(x),
+            HookTarget::Weak(ref weak) => weak.upgrade().is_some_and(|monitor| monitor.record(x)),
+        };
+        // The monitor has released all its locks by now, so the previous hook may call into it
+        if chain && record_first { hook(x); }
+        if outermost {
+            let _ = IN_HOOK.try_with(|h| h.set(false));
+            if !recovering() { let _ = UNWINDING.try_with(|u| u.set(true)); }
+        }
+        if abort {
+            eprintln!("panic_monitor: too many panics; aborting");
+            process::abort();
+        }
+    }));
+    previous
+}
+
+/// Throw away the oldest records until there are at most `max` left.
+fn evict(panicked: &mut Panicked, max: usize) {
+    let mut len: usize = panicked.values().map(Vec::len).sum();
+    while len > max {
+        // Each thread's records are in the order they were stored, so we only need to look at the
+        // first
+        let oldest = panicked.iter()
+            .min_by_key(|&(_, rs)| rs[0].seq)
+            .map(|(tid, _)| *tid)
+            .unwrap();  // `len > 0`, so the map isn't empty
+        let rs = panicked.get_mut(&oldest).unwrap();
+        rs.remove(0);
+        if rs.is_empty() { panicked.remove(&oldest); }
+        len -= 1;
+    }
+}
+
+fn non_empty<T>(xs: Vec<T>) -> Option<Vec<T>> {
+    if xs.is_empty() { None } else { Some(xs) }
+}
+
+/// Capture a backtrace of the current thread, if backtraces are enabled.  `Backtrace` isn't
+/// `Clone`, so we store it pre-formatted.
+fn capture_backtrace(enabled: Option<bool>) -> Option<String> {
+    let bt = match enabled {
+        None => Backtrace::capture(),
+        Some(true) => Backtrace::force_capture(),
+        Some(false) => return None,
+    };
+    match bt.status() {
+        BacktraceStatus::Captured => Some(bt.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "log")]
+fn log_record(record: &PanicRecord) {
+    let name = record.thread.name().unwrap_or("<unnamed>");
+    let again = if record.double_panic { " while panicking" } else { "" };
+    match record.location() {
+        Some((file, line, col)) => error!(
+            "thread '{}' panicked{} at {}:{}:{}: {}", name, again, file, line, col, record.message()
+        ),
+        None => error!("thread '{}' panicked{}: {}", name, again, record.message()),
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace_record(record: &PanicRecord) {
+    let location = record.location().map(|(file, line, col)| format!("{}:{}:{}", file, line, col));
+    tracing::error!(
+        thread.name = record.thread.name().unwrap_or("<unnamed>"),
+        thread.id = record.thread_number,
+        panic.message = record.message(),
+        panic.location = location.as_deref(),
+        panic.double = record.double_panic,
+        "thread panicked",
+    );
+}
+
+#[cfg(feature = "metrics")]
+fn report_metrics(thread_name: &str, thread_count: usize) {
+    metrics::counter!("panic_monitor.panics_total", "thread" => thread_name.to_string())
+        .increment(1);
+    metrics::gauge!("panic_monitor.panicked_threads").set(thread_count as f64);
+}
+
@@ -0,0 +1,135 @@
+//! Erlang-style supervision: restart workers when they panic.
+//!
+//! [`supervise`] spawns a worker, waits for it to finish, and respawns it if it panicked.  How
+//! long to wait before respawning, and when to give up, is decided by a [`RestartPolicy`].
+//! Since the supervisor owns the worker's `JoinHandle`, it doesn't need a `PanicMonitor`.
+//!
+//! ```
+//! use panic_monitor::supervisor::{supervise, Exit, RestartPolicy};
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! let attempts = Arc::new(AtomicUsize::new(0));
+//! let policy = RestartPolicy::fixed(Duration::from_millis(10)).max_restarts(5);
+//! let exit = supervise(|| {
+//!     let attempts = attempts.clone();
+//!     thread::spawn(move || {
+//!         // Fail twice, then succeed
+//!         if attempts.fetch_add(1, Ordering::SeqCst) < 2 { panic!("not yet"); }
+//!     })
+//! }, &policy);
+//! assert_eq!(exit, Exit::Finished { restarts: 2 });
+//! ```
+//!
+//! [`supervise`]: fn.supervise.html
+//! [`RestartPolicy`]: struct.RestartPolicy.html
+
+use std::thread::JoinHandle;
+use std::time::Duration;
+use {Clock, SystemClock};
+
+/// Decides whether, and after how long, a panicked worker should be restarted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    backoff: Backoff,
+    max_restarts: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Backoff {
+    Fixed(Duration),
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl RestartPolicy {
+    /// Restart workers as soon as they panic.
+    pub fn immediate() -> RestartPolicy {
+        RestartPolicy::fixed(Duration::from_secs(0))
+    }
+
+    /// Wait for `delay` before restarting a worker.
+    pub fn fixed(delay: Duration) -> RestartPolicy {
+        RestartPolicy { backoff: Backoff::Fixed(delay), max_restarts: None }
+    }
+
+    /// Wait for `initial` before the first restart, and twice as long before each subsequent
+    /// restart, up to a maximum of `max`.
+    pub fn exponential(initial: Duration, max: Duration) -> RestartPolicy {
+        RestartPolicy { backoff: Backoff::Exponential { initial, max }, max_restarts: None }
+    }
+
+    /// Give up once the worker has been restarted `n` times.
+    pub fn max_restarts(mut self, n: usize) -> RestartPolicy {
+        self.max_restarts = Some(n);
+        self
+    }
+
+    /// How long to wait before performing restart number `restart` (counting from zero), or
+    /// `None` if the policy says to give up.
+    pub fn delay(&self, restart: usize) -> Option<Duration> {
+        if let Some(max) = self.max_restarts {
+            if restart >= max { return None; }
+        }
+        Some(match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32.checked_shl(restart as u32).unwrap_or(u32::MAX);
+                initial.checked_mul(factor).map_or(max, |d| d.min(max))
+            }
+        })
+    }
+}
+
+/// Why [`supervise`] returned.
+///
+/// [`supervise`]: fn.supervise.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// The worker returned normally.
+    Finished { restarts: usize },
+    /// The worker panicked, and the policy said not to restart it.
+    GaveUp { restarts: usize },
+}
+
+impl Exit {
+    /// The number of times the worker was restarted.
+    pub fn restarts(&self) -> usize {
+        match *self {
+            Exit::Finished { restarts } | Exit::GaveUp { restarts } => restarts,
+        }
+    }
+}
+
+/// Spawn a worker with `spawn`, and keep respawning it whenever it panics, according to `policy`.
+///
+/// This blocks the current thread until the worker returns normally, or until the policy says to
+/// stop restarting it.
+pub fn supervise<F, T>(spawn: F, policy: &RestartPolicy) -> Exit
+where
+    F: FnMut() -> JoinHandle<T>,
+{
+    supervise_with_clock(spawn, policy, &SystemClock)
+}
+
+/// Like [`supervise`], but waits out the policy's delays using `clock`.  With a [`MockClock`],
+/// this doesn't sleep at all, which makes backoff policies quick to test.
+///
+/// [`supervise`]: fn.supervise.html
+/// [`MockClock`]: ../struct.MockClock.html
+pub fn supervise_with_clock<F, T>(mut spawn: F, policy: &RestartPolicy, clock: &dyn Clock)
+    -> Exit
+where
+    F: FnMut() -> JoinHandle<T>,
+{
+    let mut restarts = 0;
+    loop {
+        if spawn().join().is_ok() { return Exit::Finished { restarts }; }
+        match policy.delay(restarts) {
+            Some(delay) => clock.sleep(delay),
+            None => return Exit::GaveUp { restarts },
+        }
+        restarts += 1;
+    }
+}
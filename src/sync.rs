@@ -0,0 +1,68 @@
+//! The locking primitives used by the monitor.
+//!
+//! These are std's by default, or parking_lot's with the `parking_lot` feature.  Either way, they
+//! never report poisoning: a thread which panics while holding one of our locks can't leave the
+//! data in an inconsistent state (the data is valid after every individual update we make), so
+//! it's safe for us to carry on as normal.  This is important, since a supervisor is exactly the
+//! kind of thing which shouldn't be taken down by a panic elsewhere.
+
+#[cfg(not(feature = "parking_lot"))]
+mod imp {
+    pub use std::sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+    use std::sync::PoisonError;
+    use std::time::Duration;
+
+    pub fn lock<'a, T>(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        mutex.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn read<'a, T>(rwlock: &'a RwLock<T>) -> RwLockReadGuard<'a, T> {
+        rwlock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write<'a, T>(rwlock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+        rwlock.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn wait<'a, T>(cvar: &Condvar, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        cvar.wait(guard).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn wait_timeout<'a, T>(cvar: &Condvar, guard: MutexGuard<'a, T>, dur: Duration)
+        -> MutexGuard<'a, T>
+    {
+        cvar.wait_timeout(guard, dur).unwrap_or_else(PoisonError::into_inner).0
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+mod imp {
+    pub use parking_lot::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+    use std::time::Duration;
+
+    pub fn lock<'a, T>(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        mutex.lock()
+    }
+
+    pub fn read<'a, T>(rwlock: &'a RwLock<T>) -> RwLockReadGuard<'a, T> {
+        rwlock.read()
+    }
+
+    pub fn write<'a, T>(rwlock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+        rwlock.write()
+    }
+
+    pub fn wait<'a, T>(cvar: &Condvar, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        cvar.wait(&mut guard);
+        guard
+    }
+
+    pub fn wait_timeout<'a, T>(cvar: &Condvar, mut guard: MutexGuard<'a, T>, dur: Duration)
+        -> MutexGuard<'a, T>
+    {
+        cvar.wait_for(&mut guard, dur);
+        guard
+    }
+}
+
+pub use self::imp::*;
@@ -0,0 +1,167 @@
+//! Watch lists, including ones which can change while they're being waited on.
+
+use std::sync::Arc;
+use std::thread::{JoinHandle, ScopedJoinHandle, Thread, ThreadId};
+use sync::{lock, Mutex};
+use CancelToken;
+
+/// Anything which can be used as a watch list: slices, arrays, `Vec`s, and `HashSet`s of
+/// `ThreadId`s (or of anything else [`Watchable`]), and iterators over them.
+///
+/// ```
+/// # use panic_monitor::PanicMonitor;
+/// # use std::collections::HashSet;
+/// # use std::thread;
+/// # static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+/// let workers: HashSet<_> = (0..4).map(|_| thread::spawn(|| ()).thread().id()).collect();
+/// assert!(PANIC_MONITOR.check(&workers).is_empty());
+/// assert!(PANIC_MONITOR.check(workers.iter().take(2).cloned()).is_empty());
+/// ```
+///
+/// The IDs are collected up front, so an iterator is only consumed once, even by methods which
+/// scan the watch list repeatedly.  Their order is the watch-list order (which is arbitrary for a
+/// `HashSet`).
+///
+/// [`Watchable`]: trait.Watchable.html
+pub trait WatchList {
+    /// Collect the IDs into a vector.
+    fn into_ids(self) -> Vec<ThreadId>;
+}
+
+impl<I> WatchList for I where I: IntoIterator, I::Item: Watchable {
+    fn into_ids(self) -> Vec<ThreadId> {
+        self.into_iter().map(|x| x.thread_id()).collect()
+    }
+}
+
+/// The empty watch list, which watches every thread.
+///
+/// Writing `&[]` doesn't work, since there's nothing to say what it's an empty list *of*.
+///
+/// ```
+/// # use panic_monitor::{PanicMonitor, ALL_THREADS};
+/// # static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+/// if !PANIC_MONITOR.check(ALL_THREADS).is_empty() {
+///     eprintln!("some thread has panicked");
+/// }
+/// ```
+pub const ALL_THREADS: [ThreadId; 0] = [];
+
+/// Something which identifies a thread: a `ThreadId`, a `Thread`, or a `JoinHandle`.
+///
+/// This saves calling `.thread().id()` on every handle before waiting on it:
+///
+/// ```
+/// # use panic_monitor::PanicMonitor;
+/// # use std::thread;
+/// # static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+/// # PANIC_MONITOR.init();
+/// let h = thread::spawn(|| panic!());
+/// PANIC_MONITOR.wait([&h]);
+/// h.join().unwrap_err();
+/// ```
+///
+/// A `JoinHandle` is only borrowed, so it can still be joined afterwards.
+pub trait Watchable {
+    /// The ID of the thread.
+    fn thread_id(&self) -> ThreadId;
+}
+
+impl Watchable for ThreadId {
+    fn thread_id(&self) -> ThreadId { *self }
+}
+
+impl Watchable for Thread {
+    fn thread_id(&self) -> ThreadId { self.id() }
+}
+
+impl<T> Watchable for JoinHandle<T> {
+    fn thread_id(&self) -> ThreadId { self.thread().id() }
+}
+
+impl<'scope, T> Watchable for ScopedJoinHandle<'scope, T> {
+    fn thread_id(&self) -> ThreadId { self.thread().id() }
+}
+
+impl<W: Watchable + ?Sized> Watchable for &W {
+    fn thread_id(&self) -> ThreadId { (**self).thread_id() }
+}
+
+/// A shared, mutable watch list, for use with [`PanicMonitor::wait_handle`].
+///
+/// Unlike a plain slice of `ThreadId`s, a `WatchHandle` can be changed while a thread is blocked
+/// waiting on it: the waiter notices, and starts watching the new set, without having to return.
+/// This suits supervisors which discover their workers over time.  Clones of a handle share their
+/// contents.
+///
+/// [`PanicMonitor::wait_handle`]: struct.PanicMonitor.html#method.wait_handle
+#[derive(Clone, Default)]
+pub struct WatchHandle {
+    inner: Arc<Mutex<WatchSet>>,
+}
+
+#[derive(Default)]
+struct WatchSet {
+    ids: Vec<ThreadId>,    // In the order they were added, without duplicates
+    changed: CancelToken,  // Cancelled (and replaced) whenever `ids` changes
+}
+
+impl WatchSet {
+    /// Wake everyone who's waiting on the old contents.
+    fn changed(&mut self) {
+        self.changed.cancel();
+        self.changed = CancelToken::new();
+    }
+}
+
+impl WatchHandle {
+    /// Create a new, empty handle.
+    pub fn new() -> WatchHandle {
+        WatchHandle::default()
+    }
+
+    /// Start watching a thread.  Returns false if it was already being watched.
+    pub fn add(&self, tid: ThreadId) -> bool {
+        let mut set = lock(&self.inner);
+        if set.ids.contains(&tid) { return false; }
+        set.ids.push(tid);
+        set.changed();
+        true
+    }
+
+    /// Stop watching a thread.  Returns false if it wasn't being watched.
+    pub fn remove(&self, tid: ThreadId) -> bool {
+        let mut set = lock(&self.inner);
+        let len = set.ids.len();
+        set.ids.retain(|&x| x != tid);
+        if set.ids.len() == len { return false; }
+        set.changed();
+        true
+    }
+
+    /// Is the thread being watched?
+    pub fn contains(&self, tid: ThreadId) -> bool {
+        lock(&self.inner).ids.contains(&tid)
+    }
+
+    /// The watched threads, in the order they were added.
+    pub fn ids(&self) -> Vec<ThreadId> {
+        lock(&self.inner).ids.clone()
+    }
+
+    /// The number of watched threads.
+    pub fn len(&self) -> usize {
+        lock(&self.inner).ids.len()
+    }
+
+    /// Is the handle empty?
+    pub fn is_empty(&self) -> bool {
+        lock(&self.inner).ids.is_empty()
+    }
+
+    /// The current contents, along with a token which is cancelled as soon as they change.
+    pub(crate) fn snapshot(&self) -> (Vec<ThreadId>, CancelToken) {
+        let set = lock(&self.inner);
+        (set.ids.clone(), set.changed.clone())
+    }
+}
@@ -0,0 +1,34 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+use std::env;
+use std::process::Command;
+use std::time::Duration;
+
+static PANIC_MONITOR: PanicMonitor =
+    PanicMonitor::builder().abort_after(3, Duration::from_secs(60)).build();
+
+// Since the process is going to abort, the crash loop runs in a child process: this test runs
+// itself again with `CRASH_LOOP` set.
+#[test]
+fn abort_after() {
+    if env::var_os("CRASH_LOOP").is_some() {
+        PANIC_MONITOR.init();
+        panic_thread();
+        panic_thread();
+        assert_eq!(PANIC_MONITOR.panic_count(), 2);  // Not yet...
+        panic_thread();
+        unreachable!();  // ...but now
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["abort_after", "--exact", "--nocapture"])
+        .env("CRASH_LOOP", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("too many panics; aborting"), "{}", stderr);
+}
@@ -0,0 +1,23 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::env;
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// The backtrace setting is process-wide.
+#[test]
+fn backtrace() {
+    env::set_var("RUST_LIB_BACKTRACE", "1");
+    PANIC_MONITOR.init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let bt = PANIC_MONITOR.wait_records([tid])[0].backtrace().unwrap().to_string();
+    assert!(!bt.is_empty());
+    // The formatted backtrace is kept around for later callers
+    assert_eq!(PANIC_MONITOR.wait_records([tid])[0].backtrace(), Some(bt.as_str()));
+}
@@ -0,0 +1,28 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+use std::env;
+
+static FORCED: PanicMonitor =
+    PanicMonitor::builder().capture_backtrace(true).max_history(1).build();
+static NEVER: PanicMonitor = PanicMonitor::builder().capture_backtrace(false).build();
+
+// The backtrace setting is process-wide.
+#[test]
+fn builder() {
+    env::set_var("RUST_LIB_BACKTRACE", "0");
+    FORCED.init();
+    NEVER.init();
+
+    let a = panic_thread();
+    assert!(FORCED.history(a)[0].backtrace().is_some());
+    assert!(NEVER.history(a)[0].backtrace().is_none());
+
+    let b = panic_thread();
+    assert!(FORCED.check([a]).is_empty());
+    assert_eq!(FORCED.check([b]).len(), 1);
+    assert_eq!(NEVER.check([a, b]).len(), 2);
+}
@@ -0,0 +1,26 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::with_capacity(2);
+
+// Other tests' panics would fill up the history.
+#[test]
+fn capacity() {
+    PANIC_MONITOR.init();
+    assert!(PANIC_MONITOR.is_empty());
+
+    let a = panic_thread();
+    let b = panic_thread();
+    assert_eq!(PANIC_MONITOR.check([a, b]).len(), 2);
+    let c = panic_thread();
+    assert!(PANIC_MONITOR.check([a]).is_empty());
+    assert_eq!(PANIC_MONITOR.check([b, c]).len(), 2);
+    assert_eq!(PANIC_MONITOR.panicked_thread_count(), 2);
+    assert_eq!(PANIC_MONITOR.len(), 2);
+    assert!(!PANIC_MONITOR.is_empty());
+    assert_eq!(PANIC_MONITOR.panic_count(), 3);
+}
@@ -0,0 +1,40 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+static CALLBACKS_DONE: AtomicUsize = AtomicUsize::new(0);
+
+// Other tests would disturb the timings.
+#[test]
+fn check_latency() {
+    PANIC_MONITOR.init();
+
+    // A slow callback holds up each panicking thread, but it runs before the records are locked
+    PANIC_MONITOR.on_panic(|_| {
+        thread::sleep(Duration::from_millis(20));
+        CALLBACKS_DONE.fetch_add(1, Ordering::SeqCst);
+    });
+    let barrier = Arc::new(Barrier::new(33));
+    let storm: Vec<_> = (0..32).map(|_| {
+        let barrier = barrier.clone();
+        thread::spawn(move || { barrier.wait(); panic!(); })
+    }).collect();
+    let tids: Vec<_> = storm.iter().map(|h| h.thread().id()).collect();
+    barrier.wait();
+
+    // Rather than timing `check`, which would flake on a busy machine, look for a call which
+    // finished in less time than a callback takes: one during which no callback finished
+    let mut prompt = 0;
+    while PANIC_MONITOR.check(&tids).len() < tids.len() {
+        let done = CALLBACKS_DONE.load(Ordering::SeqCst);
+        PANIC_MONITOR.check(&tids);
+        if done < tids.len() && CALLBACKS_DONE.load(Ordering::SeqCst) == done { prompt += 1; }
+    }
+    assert!(prompt > 0);
+    for h in storm { h.join().unwrap_err(); }
+}
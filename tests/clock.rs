@@ -0,0 +1,45 @@
+extern crate panic_monitor;
+
+use panic_monitor::{Clock, MockClock, PanicMonitor, ALL_THREADS};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn mock_clock() {
+    let clock: &'static MockClock = Box::leak(Box::new(MockClock::new()));
+    let monitor = Arc::new(PanicMonitor::builder().clock(clock).build());
+    monitor.init_arc();
+
+    let start = clock.now();
+    clock.advance(Duration::from_secs(3600));
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    let r = &monitor.history(tid)[0];
+    assert_eq!(r.instant() - start, Duration::from_secs(3600));
+    assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+
+    // This is part of the same test, so that no other test's panics get recorded
+    frozen_clock();
+}
+
+fn frozen_clock() {
+    // The clock never moves, so every panic happens at the same instant
+    let clock: &'static MockClock = Box::leak(Box::new(MockClock::new()));
+    let monitor = Arc::new(PanicMonitor::builder().clock(clock).max_history(2).build());
+    monitor.init_arc();
+
+    let tids: Vec<_> = (0..3).map(|_| {
+        let h = thread::spawn(|| { panic!(); });
+        let tid = h.thread().id();
+        h.join().unwrap_err();
+        tid
+    }).collect();
+    // Still, the oldest record is the one which was evicted, and the rest are in order
+    let order: Vec<_> = monitor.snapshot().iter().map(|r| r.thread().id()).collect();
+    assert_eq!(order, &tids[1..]);
+    let order: Vec<_> = monitor.check(ALL_THREADS).iter().map(|t| t.id()).collect();
+    assert_eq!(order, &tids[1..]);
+    assert_eq!(monitor.wait_first(ALL_THREADS).id(), tids[1]);
+}
@@ -0,0 +1,16 @@
+//! Helpers shared by the test binaries.
+//!
+//! Most of the tests live in `test.rs`, where they share a single monitor and run concurrently.
+//! Tests which change something process-wide (the panic hook, a logger or recorder, an environment
+//! variable), or which need to know about every panic, waiter, or subscription the monitor sees,
+//! get a test binary of their own instead.
+
+use std::thread::{self, ThreadId};
+
+/// Spawn a thread which panics straight away, and wait for it to finish.
+pub fn panic_thread() -> ThreadId {
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    tid
+}
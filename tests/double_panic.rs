@@ -0,0 +1,60 @@
+extern crate panic_monitor;
+
+use panic_monitor::{PanicMonitor, PanicMonitorBuilder};
+use std::env;
+use std::panic;
+use std::process::Command;
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+struct PanicOnDrop;
+
+impl Drop for PanicOnDrop {
+    fn drop(&mut self) { panic!("second"); }
+}
+
+// Since a double panic aborts the process, it happens in a child process: this test runs itself
+// again with `DOUBLE_PANIC` set.  The child's original hook reports what the monitor recorded.
+#[test]
+fn double_panic() {
+    if env::var_os("DOUBLE_PANIC").is_some() {
+        panic::set_hook(Box::new(|_| {
+            let record = PANIC_MONITOR.history(thread::current().id()).pop().unwrap();
+            eprintln!("recorded {:?}: double={}", record.message(), record.is_double_panic());
+        }));
+        PANIC_MONITOR.init();
+        thread::spawn(|| {
+            let _guard = PanicOnDrop;
+            panic!("first");
+        }).join().unwrap_err();
+        unreachable!();
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["double_panic", "--exact", "--nocapture"])
+        .env("DOUBLE_PANIC", "1")
+        .env("RUST_BACKTRACE", "1")  // Double panics are spotted in the backtrace
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("recorded \"first\": double=false"), "{}", stderr);
+    assert!(stderr.contains("recorded \"second\": double=true"), "{}", stderr);
+}
+
+// Once the thread has recovered from its first panic, a panic in a `Drop` impl is just a panic.
+#[test]
+fn recovered() {
+    let monitor = PanicMonitorBuilder::new().capture_backtrace(true).build().leak();
+    monitor.init();
+    let h = thread::spawn(|| {
+        panic::catch_unwind(|| panic!("first")).unwrap_err();
+        panic_monitor::set_context("recovered");
+        let _guard = PanicOnDrop;
+    });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    let history = monitor.history(tid);
+    assert_eq!(history.len(), 2);
+    assert!(!history[1].is_double_panic());
+}
@@ -0,0 +1,39 @@
+extern crate panic_monitor;
+
+use panic_monitor::ALL_THREADS;
+use std::thread;
+
+#[test]
+fn global() {
+    // The global monitor isn't initialised until it's first used
+    let early = thread::spawn(|| { panic!(); });
+    let early_tid = early.thread().id();
+    early.join().unwrap_err();
+    assert!(panic_monitor::check(ALL_THREADS).is_empty());
+    assert!(panic_monitor::global().is_initialized());
+
+    panic_monitor::init();
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    assert_eq!(panic_monitor::wait([&h])[0].id(), tid);
+    assert_eq!(panic_monitor::check([tid, early_tid]).len(), 1);
+    assert_eq!(panic_monitor::global().history(tid).len(), 1);
+    h.join().unwrap_err();
+
+    // Once the hook has been removed, only `init` puts it back
+    panic_monitor::global().uninstall();
+    assert_eq!(panic_monitor::check([tid]).len(), 1);
+    assert!(!panic_monitor::global().is_initialized());
+    // Using the monitor while panicking is fine, even though the hook can't be installed then
+    thread::spawn(|| {
+        struct Init;
+        impl Drop for Init {
+            fn drop(&mut self) { panic_monitor::init(); }
+        }
+        let _init = Init;
+        panic!();
+    }).join().unwrap_err();
+    assert!(!panic_monitor::global().is_initialized());
+    panic_monitor::init();
+    assert!(panic_monitor::global().is_initialized());
+}
@@ -0,0 +1,41 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::panic;
+use std::sync::Mutex;
+use std::thread;
+
+static BEFORE: PanicMonitor = PanicMonitor::new();
+static AFTER: PanicMonitor = PanicMonitor::new();
+static EVENTS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+fn log(event: &'static str) {
+    EVENTS.lock().unwrap().push(event);
+}
+
+#[test]
+fn hook_order() {
+    // Another crate's hook, which was installed first
+    panic::set_hook(Box::new(|_| log("base")));
+    BEFORE.on_panic(|_| log("before"));
+    BEFORE.init_before();
+    AFTER.on_panic(|_| log("after"));
+    AFTER.init_after();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    // AFTER's hook calls BEFORE's first, which records and then calls the base hook
+    assert_eq!(*EVENTS.lock().unwrap(), ["before", "base", "after"]);
+
+    // Chaining the hooks doesn't make either monitor think that it saw a double panic
+    assert!(!BEFORE.history(tid)[0].is_double_panic());
+    assert!(!AFTER.history(tid)[0].is_double_panic());
+
+    // The order survives reinstalling, after someone else has clobbered the hooks
+    EVENTS.lock().unwrap().clear();
+    panic::set_hook(Box::new(|_| log("base")));
+    AFTER.reinstall();
+    thread::spawn(|| { panic!(); }).join().unwrap_err();
+    assert_eq!(*EVENTS.lock().unwrap(), ["base", "after"]);
+}
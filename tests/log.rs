@@ -0,0 +1,39 @@
+#![cfg(feature = "log")]
+
+extern crate log;
+extern crate panic_monitor;
+
+use log::{Level, Log, Metadata, Record};
+use panic_monitor::PanicMonitor;
+use std::sync::Mutex;
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new().log_panics(true);
+
+static LOGGED: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+struct TestLogger;
+
+impl Log for TestLogger {
+    fn enabled(&self, _: &Metadata) -> bool { true }
+    fn log(&self, record: &Record) {
+        LOGGED.lock().unwrap().push((record.level(), record.args().to_string()));
+    }
+    fn flush(&self) {}
+}
+
+#[test]
+fn log_panics() {
+    log::set_logger(&TestLogger).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+    PANIC_MONITOR.init();
+
+    let builder = thread::Builder::new().name("log-test".into());
+    builder.spawn(|| { panic!("oops"); }).unwrap().join().unwrap_err();
+
+    let logged = LOGGED.lock().unwrap();
+    assert_eq!(logged.len(), 1);
+    assert_eq!(logged[0].0, Level::Error);
+    assert!(logged[0].1.starts_with(&format!("thread 'log-test' panicked at {}:", file!())));
+    assert!(logged[0].1.ends_with(": oops"));
+}
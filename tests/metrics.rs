@@ -0,0 +1,71 @@
+#![cfg(feature = "metrics")]
+
+extern crate metrics;
+extern crate panic_monitor;
+
+use metrics::{Counter, CounterFn, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata, Recorder};
+use metrics::{SharedString, Unit};
+use panic_monitor::PanicMonitor;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new().record_metrics(true);
+
+type Labels = Vec<(String, String)>;
+
+static COUNTERS: Mutex<Vec<(String, Labels, u64)>> = Mutex::new(Vec::new());
+static GAUGES: Mutex<Vec<(String, f64)>> = Mutex::new(Vec::new());
+
+struct TestRecorder;
+
+struct TestCounter(String, Labels);
+
+impl CounterFn for TestCounter {
+    fn increment(&self, value: u64) {
+        COUNTERS.lock().unwrap().push((self.0.clone(), self.1.clone(), value));
+    }
+    fn absolute(&self, _: u64) {}  // Not used by the monitor
+}
+
+struct TestGauge(String);
+
+impl GaugeFn for TestGauge {
+    fn increment(&self, _: f64) {}  // Not used by the monitor
+    fn decrement(&self, _: f64) {}
+    fn set(&self, value: f64) { GAUGES.lock().unwrap().push((self.0.clone(), value)); }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn register_counter(&self, key: &Key, _: &Metadata) -> Counter {
+        let labels = key.labels().map(|l| (l.key().to_string(), l.value().to_string())).collect();
+        Counter::from_arc(Arc::new(TestCounter(key.name().to_string(), labels)))
+    }
+    fn register_gauge(&self, key: &Key, _: &Metadata) -> Gauge {
+        Gauge::from_arc(Arc::new(TestGauge(key.name().to_string())))
+    }
+    fn register_histogram(&self, _: &Key, _: &Metadata) -> Histogram { Histogram::noop() }
+}
+
+#[test]
+fn record_metrics() {
+    metrics::set_global_recorder(TestRecorder).unwrap();
+    PANIC_MONITOR.init();
+
+    for _ in 0..2 {
+        let builder = thread::Builder::new().name("metrics-test".into());
+        builder.spawn(|| { panic!("oops"); }).unwrap().join().unwrap_err();
+    }
+
+    let label = vec![("thread".to_string(), "metrics-test".to_string())];
+    assert_eq!(*COUNTERS.lock().unwrap(), vec![
+        ("panic_monitor.panics_total".to_string(), label.clone(), 1),
+        ("panic_monitor.panics_total".to_string(), label, 1),
+    ]);
+    assert_eq!(*GAUGES.lock().unwrap(), vec![
+        ("panic_monitor.panicked_threads".to_string(), 1.0),
+        ("panic_monitor.panicked_threads".to_string(), 2.0),
+    ]);
+}
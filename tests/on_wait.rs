@@ -0,0 +1,49 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+static OBSERVED: Mutex<Vec<(Vec<ThreadId>, Duration)>> = Mutex::new(Vec::new());
+
+// The callback would see other tests' waits.
+#[test]
+fn on_wait() {
+    PANIC_MONITOR.init();
+    PANIC_MONITOR.on_wait(|threads, elapsed| {
+        OBSERVED.lock().unwrap().push((threads.iter().map(|t| t.id()).collect(), elapsed));
+    });
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!("slow"); });
+    let tid = h.thread().id();
+    assert_eq!(PANIC_MONITOR.wait_records([tid])[0].message(), "slow");
+    {
+        let observed = OBSERVED.lock().unwrap();
+        assert_eq!(observed.len(), 1);
+        assert_eq!(observed[0].0, vec![tid]);
+        assert!(observed[0].1 >= Duration::from_millis(40));
+    }
+
+    // Timeouts aren't observations, but panics which had already happened are
+    let good = thread::spawn(|| {});
+    assert!(PANIC_MONITOR.wait_timeout([good.thread().id()], Duration::from_millis(10)).is_empty());
+    assert_eq!(OBSERVED.lock().unwrap().len(), 1);
+    assert_eq!(PANIC_MONITOR.wait_timeout([tid], Duration::from_secs(10))[0].id(), tid);
+    assert_eq!(OBSERVED.lock().unwrap().len(), 2);
+
+    // The other blocking waits are observations too
+    assert_eq!(PANIC_MONITOR.wait_first([tid]).id(), tid);
+    assert_eq!(PANIC_MONITOR.wait_all([tid]).len(), 1);
+    assert_eq!(PANIC_MONITOR.cursor([tid]).wait().len(), 1);
+    assert_eq!(panic_monitor::wait_any(&[(&PANIC_MONITOR, &[tid])]).0, 0);
+    assert_eq!(OBSERVED.lock().unwrap().len(), 6);
+    assert!(OBSERVED.lock().unwrap()[2..].iter().all(|(ids, _)| *ids == [tid]));
+    // But checks aren't, and nor is a wait whose deadline has already passed
+    assert_eq!(PANIC_MONITOR.check([tid]).len(), 1);
+    assert_eq!(PANIC_MONITOR.wait_deadline([tid], Instant::now()).len(), 1);
+    assert_eq!(OBSERVED.lock().unwrap().len(), 6);
+    h.join().unwrap_err();
+    good.join().unwrap();
+}
@@ -0,0 +1,42 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::sync::mpsc;
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// Pruning would interfere with other tests' threads.
+#[test]
+fn prune() {
+    PANIC_MONITOR.init();
+
+    let (dead, dead_id) = PANIC_MONITOR.spawn("dead", || { panic!(); }).unwrap();
+    let (tx, rx) = mpsc::channel::<()>();
+    let (alive, alive_id) = PANIC_MONITOR.spawn("alive", move || {
+        // Panic, but keep running
+        std::panic::catch_unwind(|| panic!()).unwrap_err();
+        rx.recv().ok();
+    }).unwrap();
+    let other = thread::spawn(|| { panic!(); });
+    let other_id = other.thread().id();
+    dead.join().unwrap_err();
+    other.join().unwrap_err();
+    PANIC_MONITOR.wait_all([dead_id, alive_id, other_id]);
+
+    assert_eq!(PANIC_MONITOR.prune(), 1);
+    assert!(!PANIC_MONITOR.has_panicked(dead_id));
+    assert!(PANIC_MONITOR.has_panicked(alive_id));
+    assert!(PANIC_MONITOR.has_panicked(other_id));  // Not spawned by us, so we can't tell
+    assert_eq!(PANIC_MONITOR.panic_count_for(dead_id), 0);
+    assert_eq!(PANIC_MONITOR.thread_number(dead_id), None);
+    assert!(PANIC_MONITOR.thread_number(alive_id).is_some());
+    assert_eq!(PANIC_MONITOR.panic_count_for(alive_id), 1);
+    assert_eq!(PANIC_MONITOR.spawned(), vec![alive_id]);
+
+    drop(tx);
+    alive.join().unwrap();
+    assert_eq!(PANIC_MONITOR.prune(), 1);
+    assert!(PANIC_MONITOR.spawned().is_empty());
+    assert_eq!(PANIC_MONITOR.thread_number(alive_id), None);
+}
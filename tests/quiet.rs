@@ -0,0 +1,21 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::builder().chain_previous_hook(false).build();
+static PREVIOUS_HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn quiet() {
+    panic::set_hook(Box::new(|_| { PREVIOUS_HOOK_CALLS.fetch_add(1, Ordering::SeqCst); }));
+    PANIC_MONITOR.init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert!(PANIC_MONITOR.has_panicked(tid));
+    assert_eq!(PREVIOUS_HOOK_CALLS.load(Ordering::SeqCst), 0);
+}
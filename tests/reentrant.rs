@@ -0,0 +1,90 @@
+#[cfg(feature = "futures")] extern crate futures;
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Wake, Waker};
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+static SEEN_BY_CALLBACK: AtomicUsize = AtomicUsize::new(0);
+static SEEN_BY_PREVIOUS_HOOK: AtomicUsize = AtomicUsize::new(0);
+static WOKEN: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "futures")]
+static STREAM_WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+// A waker which polls on the spot, as some executors do, and so calls back into the monitor
+struct Reentrant;
+
+impl Wake for Reentrant {
+    fn wake(self: Arc<Self>) {
+        WOKEN.fetch_add(1, Ordering::SeqCst);
+        let waker = Waker::from(self);
+        assert!(PANIC_MONITOR.poll_wait(&[], &mut Context::from_waker(&waker)).is_ready());
+        PANIC_MONITOR.register_waker(&[], waker);
+    }
+}
+
+// A stream's task which polls on the spot too, and subscribes to the monitor while it's at it
+#[cfg(feature = "futures")]
+struct Subscribing;
+
+#[cfg(feature = "futures")]
+impl Wake for Subscribing {
+    fn wake(self: Arc<Self>) {
+        STREAM_WOKEN.fetch_add(1, Ordering::SeqCst);
+        assert!(PANIC_MONITOR.subscriber_count() > 0);
+        drop(PANIC_MONITOR.stream(&[]));
+    }
+}
+
+#[test]
+fn reentrant() {
+    // Both the previous hook and the callback call back into the monitor from inside the hook
+    panic::set_hook(Box::new(|_| {
+        let me = thread::current().id();
+        if PANIC_MONITOR.check([me]).len() == 1 {
+            SEEN_BY_PREVIOUS_HOOK.fetch_add(1, Ordering::SeqCst);
+        }
+    }));
+    PANIC_MONITOR.init();
+    PANIC_MONITOR.on_panic(|t| {
+        assert!(!PANIC_MONITOR.has_panicked(t.id()));  // Not recorded yet
+        PANIC_MONITOR.on_panic(|_| {});
+        SEEN_BY_CALLBACK.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert!(PANIC_MONITOR.has_panicked(tid));
+    assert_eq!(SEEN_BY_CALLBACK.load(Ordering::SeqCst), 1);
+    assert_eq!(SEEN_BY_PREVIOUS_HOOK.load(Ordering::SeqCst), 1);
+
+    // Wakers are woken with none of the monitor's locks held
+    PANIC_MONITOR.register_waker(&[], Waker::from(Arc::new(Reentrant)));
+    thread::spawn(|| { panic!(); }).join().unwrap_err();
+    assert_eq!(WOKEN.load(Ordering::SeqCst), 1);
+
+    // And so are streams' tasks
+    #[cfg(feature = "futures")]
+    {
+        use futures::Stream;
+        use std::task::Poll;
+
+        let waker = Waker::from(Arc::new(Subscribing));
+        let mut cx = Context::from_waker(&waker);
+        let mut stream = std::pin::pin!(PANIC_MONITOR.stream(&[]));
+        assert!(stream.as_mut().poll_next(&mut cx).is_pending());
+        let h = thread::spawn(|| { panic!(); });
+        let tid = h.thread().id();
+        h.join().unwrap_err();
+        assert_eq!(STREAM_WOKEN.load(Ordering::SeqCst), 1);
+        match stream.as_mut().poll_next(&mut cx) {
+            Poll::Ready(Some(record)) => assert_eq!(record.thread().id(), tid),
+            _ => panic!("the stream should have a record"),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+use std::panic;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+#[test]
+fn reinstall() {
+    PANIC_MONITOR.init();
+    PANIC_MONITOR.init();
+    let tid = panic_thread();
+    assert_eq!(PANIC_MONITOR.history(tid).len(), 1);
+
+    // Someone else clobbers our hook...
+    panic::set_hook(Box::new(|_| {}));
+    let tid = panic_thread();
+    assert!(PANIC_MONITOR.check([tid]).is_empty());
+    // ...and calling init again doesn't help...
+    PANIC_MONITOR.init();
+    let tid = panic_thread();
+    assert!(PANIC_MONITOR.check([tid]).is_empty());
+    // ...but reinstall does
+    PANIC_MONITOR.reinstall();
+    let tid = panic_thread();
+    assert_eq!(PANIC_MONITOR.history(tid).len(), 1);
+}
@@ -0,0 +1,31 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+#[test]
+fn reset() {
+    PANIC_MONITOR.init();
+    let before = panic_thread();
+    assert_eq!(PANIC_MONITOR.panic_count(), 1);
+
+    PANIC_MONITOR.reset();
+    assert!(!PANIC_MONITOR.has_panicked(before));
+    assert_eq!(PANIC_MONITOR.panic_count_for(before), 0);
+    assert_eq!(PANIC_MONITOR.thread_number(before), None);
+    assert!(!PANIC_MONITOR.ever_panicked(before));
+    assert_eq!(PANIC_MONITOR.panic_count(), 0);
+    // The hook has gone...
+    let tid = panic_thread();
+    assert!(!PANIC_MONITOR.has_panicked(tid));
+    // ...until we init again
+    PANIC_MONITOR.init();
+    let tid = panic_thread();
+    assert!(PANIC_MONITOR.has_panicked(tid));
+    assert_eq!(PANIC_MONITOR.panic_count(), 1);
+    assert_eq!(PANIC_MONITOR.thread_number(tid), Some(0));  // The numbering starts again
+}
@@ -0,0 +1,51 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// Other tests' waiters would affect the counts.
+#[test]
+fn stats() {
+    PANIC_MONITOR.init();
+    assert_eq!(PANIC_MONITOR.stats().wakeups(), 0);
+
+    let a = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let b = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); panic!(); });
+    // Woken once by `a` (not enough) and once by `b`
+    PANIC_MONITOR.wait_all([a.thread().id(), b.thread().id()]);
+    let stats = PANIC_MONITOR.stats();
+    assert_eq!(stats.wakeups(), 2);
+    assert_eq!(stats.panic_wakeups(), 1);
+    assert_eq!(stats.spurious_wakeups(), 1);
+
+    assert_eq!(stats.coalesced_notifications(), 0);
+
+    // Returning straight away doesn't count
+    PANIC_MONITOR.wait([a.thread().id()]);
+    assert_eq!(PANIC_MONITOR.stats(), stats);
+    a.join().unwrap_err();
+    b.join().unwrap_err();
+
+    // In a crash storm, each notification either wakes the waiter or is coalesced into a pending
+    // one (or arrives after the waiter has already seen everything and left)
+    PANIC_MONITOR.reset();
+    PANIC_MONITOR.init();
+    let barrier = Arc::new(Barrier::new(9));
+    let storm: Vec<_> = (0..8).map(|_| {
+        let barrier = barrier.clone();
+        thread::spawn(move || { barrier.wait(); panic!(); })
+    }).collect();
+    let tids: Vec<_> = storm.iter().map(|h| h.thread().id()).collect();
+    let waiter = thread::spawn(move || PANIC_MONITOR.wait_all(tids).len());
+    while PANIC_MONITOR.waiter_count() == 0 { thread::sleep(Duration::from_millis(1)); }
+    barrier.wait();
+    assert_eq!(waiter.join().unwrap(), 8);
+    let stats = PANIC_MONITOR.stats();
+    assert!(stats.wakeups() >= 1);
+    assert!(stats.wakeups() + stats.coalesced_notifications() <= 8);
+    for h in storm { h.join().unwrap_err(); }
+}
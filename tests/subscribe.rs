@@ -0,0 +1,33 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::{PanicMonitor, ALL_THREADS};
+use std::thread;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// Other tests' subscriptions would throw the count off.
+#[test]
+fn dropped_receivers_are_pruned() {
+    PANIC_MONITOR.init();
+
+    let other = thread::current().id();
+    let watching_other = PANIC_MONITOR.subscribe([other]);
+    let watching_all = PANIC_MONITOR.subscribe(ALL_THREADS);
+    let kept = PANIC_MONITOR.subscribe(ALL_THREADS);
+    assert_eq!(PANIC_MONITOR.subscriber_count(), 3);
+    drop(watching_other);
+    drop(watching_all);
+
+    let tid = panic_thread();
+    assert_eq!(kept.recv().unwrap().id(), tid);
+    // The one watching everything noticed; the one watching the current thread hasn't had the
+    // chance yet
+    assert_eq!(PANIC_MONITOR.subscriber_count(), 2);
+
+    drop(kept);
+    panic_thread();
+    assert_eq!(PANIC_MONITOR.subscriber_count(), 1);
+}
@@ -0,0 +1,45 @@
+extern crate panic_monitor;
+
+use panic_monitor::MockClock;
+use panic_monitor::supervisor::{supervise, supervise_with_clock, Exit, RestartPolicy};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn gives_up() {
+    let policy = RestartPolicy::immediate().max_restarts(3);
+    let exit = supervise(|| thread::spawn(|| { panic!(); }), &policy);
+    assert_eq!(exit, Exit::GaveUp { restarts: 3 });
+}
+
+#[test]
+fn finished() {
+    let exit = supervise(|| thread::spawn(|| ()), &RestartPolicy::immediate());
+    assert_eq!(exit, Exit::Finished { restarts: 0 });
+}
+
+#[test]
+fn backoff() {
+    let ms = Duration::from_millis;
+    let policy = RestartPolicy::exponential(ms(10), ms(35));
+    let delays: Vec<_> = (0..4).map(|n| policy.delay(n).unwrap()).collect();
+    assert_eq!(delays, vec![ms(10), ms(20), ms(35), ms(35)]);
+    assert_eq!(policy.delay(1000), Some(ms(35)));
+
+    let start = Instant::now();
+    let exit = supervise(|| thread::spawn(|| { panic!(); }), &policy.max_restarts(2));
+    assert_eq!(exit.restarts(), 2);
+    assert!(start.elapsed() >= ms(30));
+}
+
+#[test]
+fn mock_clock() {
+    let ms = Duration::from_millis;
+    let clock = MockClock::new();
+    let policy = RestartPolicy::exponential(ms(1000), ms(60_000)).max_restarts(3);
+    let start = Instant::now();
+    let exit = supervise_with_clock(|| thread::spawn(|| { panic!(); }), &policy, &clock);
+    assert_eq!(exit, Exit::GaveUp { restarts: 3 });
+    assert_eq!(clock.elapsed(), ms(7000));
+    assert!(start.elapsed() < ms(7000));
+}
@@ -1,14 +1,28 @@
+// `test` borrows its watch lists, as code did before `WatchList` existed, to check that such code
+// still compiles
+#![allow(clippy::needless_borrows_for_generic_args)]
+
+#[cfg(feature = "crossbeam")] extern crate crossbeam_channel;
+#[cfg(feature = "futures")] extern crate futures;
 #[macro_use] extern crate lazy_static;
+#[cfg(feature = "serde")] extern crate serde_json;
 extern crate panic_monitor;
 
-use panic_monitor::PanicMonitor;
+use panic_monitor::{
+    CancelToken, Outcome, PanicMonitor, Payload, ThreadGroup, WatchHandle, ALL_THREADS,
+};
 use std::thread::{self, ThreadId};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 lazy_static! {
     static ref PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
 }
 
+/// Every test calls this, since the tests can run in any order.  Repeated calls are harmless.
+fn init() {
+    PANIC_MONITOR.init();
+}
+
 #[test]
 fn test() {
     // Initialise the PanicMonitor
@@ -28,3 +42,1156 @@ fn test() {
 
     watcher.join().unwrap();
 }
+
+#[test]
+fn records() {
+    init();
+
+    let fmt = thread::spawn(|| { panic!("worker {} died", 3); });
+    let lit = thread::spawn(|| { panic!("worker died"); });
+    let any = thread::spawn(|| { std::panic::panic_any(42); });
+    let ids = [fmt.thread().id(), lit.thread().id(), any.thread().id()];
+    fmt.join().unwrap_err();
+    lit.join().unwrap_err();
+    any.join().unwrap_err();
+
+    let rs = PANIC_MONITOR.check_records(ids);
+    let msgs: Vec<&str> = rs.iter().map(|r| r.message()).collect();
+    assert_eq!(msgs, vec!["worker 3 died", "worker died", "Box<dyn Any>"]);
+    assert_eq!(*rs[1].payload(), Payload::Str("worker died".to_string()));
+    assert_eq!(*rs[2].payload(), Payload::Other);
+    let tids: Vec<ThreadId> = rs.iter().map(|r| r.thread().id()).collect();
+    assert_eq!(tids, ids);
+    assert_eq!(PANIC_MONITOR.wait_records(ids).len(), 3);
+    assert_eq!(PANIC_MONITOR.wait_timeout_records(ids, Duration::from_millis(10)).len(), 3);
+}
+
+#[test]
+fn location() {
+    init();
+
+    let h = thread::spawn(|| { panic!(); }); let line = line!();
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let rs = PANIC_MONITOR.check_records([tid]);
+    let (file, l, _) = rs[0].location().unwrap();
+    assert_eq!(file, file!());
+    assert_eq!(l, line);
+}
+
+#[test]
+fn timestamps() {
+    init();
+
+    let (before, before_sys) = (Instant::now(), SystemTime::now());
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    let (after, after_sys) = (Instant::now(), SystemTime::now());
+
+    let r = &PANIC_MONITOR.check_records([tid])[0];
+    assert!(before <= r.instant() && r.instant() <= after);
+    assert!(before_sys <= r.system_time() && r.system_time() <= after_sys);
+}
+
+#[test]
+fn wait_all() {
+    init();
+
+    let a = thread::spawn(|| { panic!(); }).thread().id();
+    let b = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); }).thread().id();
+    let t = PANIC_MONITOR.wait_all([a, b, a]);
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![a, b]);
+    assert!(PANIC_MONITOR.wait_all(Vec::<ThreadId>::new()).is_empty());
+}
+
+#[test]
+fn try_wait() {
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let tid = h.thread().id();
+    assert!(PANIC_MONITOR.try_wait([tid]).is_none());
+    h.join().unwrap_err();
+    let t = PANIC_MONITOR.try_wait([tid]).unwrap();
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![tid]);
+}
+
+#[test]
+fn wait_deadline() {
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); panic!(); });
+    let tid = h.thread().id();
+    let start = Instant::now();
+    assert!(PANIC_MONITOR.wait_deadline([tid], start).is_empty());
+    assert!(PANIC_MONITOR.wait_deadline([tid], start + Duration::from_millis(50)).is_empty());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    let t = PANIC_MONITOR.wait_deadline([tid], start + Duration::from_secs(10));
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![tid]);
+    // A deadline in the past still reports threads which have already panicked
+    assert_eq!(PANIC_MONITOR.wait_deadline([tid], start).len(), 1);
+    h.join().unwrap_err();
+}
+
+#[test]
+fn deadline_records() {
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!("late"); });
+    let tid = h.thread().id();
+    assert!(PANIC_MONITOR.try_wait_records([tid]).is_none());
+    let rs = PANIC_MONITOR.wait_deadline_records([tid], Instant::now() + Duration::from_secs(10));
+    assert_eq!(rs.len(), 1);
+    assert_eq!(rs[0].thread().id(), tid);
+    assert_eq!(rs[0].message(), "late");
+    let rs = PANIC_MONITOR.try_wait_records([tid]).unwrap();
+    assert_eq!(rs[0].message(), "late");
+    h.join().unwrap_err();
+}
+
+#[test]
+fn panic_count() {
+    init();
+
+    // Other tests are panicking concurrently, so we can only check lower bounds
+    let (events, threads) = (PANIC_MONITOR.panic_count(), PANIC_MONITOR.panicked_thread_count());
+    let h = thread::spawn(|| {
+        let _ = std::panic::catch_unwind(|| panic!());
+        panic!();
+    });
+    h.join().unwrap_err();
+    assert!(PANIC_MONITOR.panic_count() >= events + 2);
+    assert!(PANIC_MONITOR.panicked_thread_count() > threads);
+}
+
+#[test]
+fn history() {
+    init();
+
+    let h = thread::spawn(|| {
+        let _ = std::panic::catch_unwind(|| panic!("first"));
+        panic!("second");
+    });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let history = PANIC_MONITOR.history(tid);
+    let msgs: Vec<&str> = history.iter().map(|r| r.message()).collect();
+    assert_eq!(msgs, vec!["first", "second"]);
+    // The thread recovered from the first panic, so the second one isn't a double panic
+    assert!(!history[0].is_double_panic());
+    assert!(!history[1].is_double_panic());
+    assert_eq!(PANIC_MONITOR.check_records([tid])[0].message(), "second");
+    assert!(PANIC_MONITOR.history(thread::current().id()).is_empty());
+    assert_eq!(PANIC_MONITOR.panic_count_for(tid), 2);
+    assert_eq!(PANIC_MONITOR.panic_count_for(thread::current().id()), 0);
+    // Forgetting the records doesn't reset the count
+    PANIC_MONITOR.forget(tid);
+    assert_eq!(PANIC_MONITOR.panic_count_for(tid), 2);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn stream() {
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    init();
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let early = thread::spawn(|| { panic!("early"); });
+    let early_id = early.thread().id();
+    early.join().unwrap_err();
+    let h = thread::spawn(move || {
+        rx.recv().unwrap();
+        let _ = std::panic::catch_unwind(|| panic!("first"));
+        panic!("second");
+    });
+    let tid = h.thread().id();
+
+    let mut stream = PANIC_MONITOR.stream(&[early_id, tid]);
+    tx.send(()).unwrap();
+    assert_eq!(block_on(stream.next()).unwrap().message(), "first");
+    assert_eq!(block_on(stream.next()).unwrap().message(), "second");
+    h.join().unwrap_err();
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn wait_timeout_async() {
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    init();
+
+    // A timer which goes off after `dur`, without needing a runtime
+    fn timer(dur: Duration) -> impl std::future::Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || { thread::sleep(dur); let _ = tx.send(()); });
+        rx.map(|_| ())
+    }
+
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); });
+    let good_id = good.thread().id();
+    let start = Instant::now();
+    let timeout = timer(Duration::from_millis(20));
+    let t = block_on(PANIC_MONITOR.wait_timeout_async(&[good_id], timeout));
+    assert!(t.is_empty());
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!(); });
+    let tid = h.thread().id();
+    let t = block_on(PANIC_MONITOR.wait_timeout_async(&[tid], timer(Duration::from_secs(10))));
+    assert_eq!(t[0].id(), tid);
+    // A panic which has already happened wins, even if the timer has gone off too
+    let t = block_on(PANIC_MONITOR.wait_timeout_async(&[tid], futures::future::ready(())));
+    assert_eq!(t[0].id(), tid);
+    h.join().unwrap_err();
+    good.join().unwrap();
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn wait_async() {
+    use futures::executor::block_on;
+
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let tid = h.thread().id();
+    let t = block_on(PANIC_MONITOR.wait_async(&[tid]));
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![tid]);
+    // Already-panicked threads resolve immediately
+    assert_eq!(block_on(PANIC_MONITOR.wait_async(&[tid])).len(), 1);
+    h.join().unwrap_err();
+}
+
+#[test]
+fn poll_wait() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct CountWakes(AtomicUsize);
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) { self.0.fetch_add(1, Ordering::SeqCst); }
+    }
+
+    init();
+
+    let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+    let waker = Waker::from(wakes.clone());
+    let mut cx = Context::from_waker(&waker);
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!(); });
+    let tid = h.thread().id();
+    // Polling repeatedly only registers the waker once
+    assert!(PANIC_MONITOR.poll_wait(&[tid], &mut cx).is_pending());
+    assert!(PANIC_MONITOR.poll_wait(&[tid], &mut cx).is_pending());
+    h.join().unwrap_err();
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    match PANIC_MONITOR.poll_wait(&[tid], &mut cx) {
+        Poll::Ready(ts) => assert_eq!(ts[0].id(), tid),
+        Poll::Pending => panic!("thread should have panicked"),
+    }
+}
+
+#[test]
+fn subscribe() {
+    init();
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let h = thread::spawn(move || {
+        rx.recv().unwrap();
+        let _ = std::panic::catch_unwind(|| panic!());
+        panic!();
+    });
+    let tid = h.thread().id();
+
+    let panics = PANIC_MONITOR.subscribe([tid]);
+    tx.send(()).unwrap();
+    assert_eq!(panics.recv().unwrap().id(), tid);
+    assert_eq!(panics.recv().unwrap().id(), tid);
+    h.join().unwrap_err();
+    assert!(panics.try_recv().is_err());
+}
+
+#[test]
+fn forget() {
+    init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.check([tid]).len(), 1);
+    PANIC_MONITOR.forget(tid);
+    assert!(PANIC_MONITOR.check([tid]).is_empty());
+    assert!(PANIC_MONITOR.history(tid).is_empty());
+    assert_eq!(PANIC_MONITOR.thread_number(tid), None);
+    assert!(PANIC_MONITOR.wait_timeout([tid], Duration::from_millis(10)).is_empty());
+    // ...but the monitor still remembers that it happened
+    assert!(!PANIC_MONITOR.has_panicked(tid));
+    assert!(PANIC_MONITOR.ever_panicked(tid));
+    assert!(!PANIC_MONITOR.ever_panicked(thread::current().id()));
+}
+
+#[test]
+fn spawn() {
+    init();
+
+    let (h, tid) = PANIC_MONITOR.spawn("spawn-test", || { panic!(); }).unwrap();
+    assert_eq!(h.thread().id(), tid);
+    assert_eq!(h.thread().name(), Some("spawn-test"));
+    let builder = thread::Builder::new().name("spawn-with-test".into()).stack_size(64 * 1024);
+    let (h2, tid2) = PANIC_MONITOR.spawn_with(builder, || 5).unwrap();
+    let spawned = PANIC_MONITOR.spawned();
+    assert!(spawned.contains(&tid) && spawned.contains(&tid2));
+
+    let t = PANIC_MONITOR.wait([tid, tid2]);
+    assert_eq!(t[0].name(), Some("spawn-test"));
+    h.join().unwrap_err();
+    assert_eq!(h2.join().unwrap(), 5);
+}
+
+#[test]
+fn arc() {
+    let monitor = PanicMonitor::new_arc();
+    monitor.init_arc();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert_eq!(monitor.wait([tid])[0].id(), tid);
+
+    // Once the monitor is gone, its hook no longer does anything
+    let weak = std::sync::Arc::downgrade(&monitor);
+    drop(monitor);
+    assert!(weak.upgrade().is_none());
+    thread::spawn(|| { panic!(); }).join().unwrap_err();
+}
+
+#[test]
+fn leak() {
+    let monitor = PanicMonitor::new().leak();
+    monitor.init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert_eq!(monitor.wait([tid])[0].id(), tid);
+}
+
+#[test]
+fn default() {
+    fn make<T: Default>() -> T { T::default() }
+    let monitor: PanicMonitor = make();
+    assert!(!monitor.is_initialized());
+    assert_eq!(monitor.panic_count(), 0);
+    let built = make::<panic_monitor::PanicMonitorBuilder>().build();
+    assert!(!built.is_initialized());
+}
+
+#[test]
+fn scoped() {
+    init();
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    thread::scope(|s| {
+        let healthy = s.spawn(move || { let _ = rx.recv(); 5 });
+        let broken = s.spawn(|| { panic!("scoped"); });
+        let tids = [healthy.thread().id(), broken.thread().id()];
+
+        // Observable before the scope joins anyone, and while `healthy` is still running
+        assert_eq!(PANIC_MONITOR.wait(tids)[0].id(), tids[1]);
+        assert_eq!(PANIC_MONITOR.wait_records(tids)[0].message(), "scoped");
+        assert!(!healthy.is_finished());
+
+        drop(tx);
+        broken.join().unwrap_err();
+        assert_eq!(healthy.join().unwrap(), 5);
+    });
+}
+
+#[test]
+fn snapshot() {
+    init();
+
+    let builder = thread::Builder::new().name("snapshot-test".into());
+    let h = builder.spawn(|| { panic!("oops"); }).unwrap();
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let snapshot = PANIC_MONITOR.snapshot();
+    let ours: Vec<_> = snapshot.iter().filter(|r| r.thread().id() == tid).collect();
+    assert_eq!(ours.len(), 1);
+    assert_eq!(ours[0].thread_number(), PANIC_MONITOR.check_records([tid])[0].thread_number());
+    // Records are in the order they were stored.  That's the order they happened in for any one
+    // thread, but concurrent panics may have been stored in the other order to their timestamps.
+    for (i, r) in snapshot.iter().enumerate() {
+        let later = &snapshot[i + 1..];
+        let tid = r.thread().id();
+        let mut same_thread = later.iter().filter(|l| l.thread().id() == tid);
+        assert!(same_thread.all(|l| r.instant() <= l.instant()));
+    }
+}
+
+#[test]
+fn for_each() {
+    init();
+
+    let h = thread::spawn(|| {
+        let _ = std::panic::catch_unwind(|| panic!("first"));
+        panic!("second");
+    });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let mut ours = vec![];
+    let mut total = 0;
+    PANIC_MONITOR.for_each(|r| {
+        total += 1;
+        if r.thread().id() == tid { ours.push(r.message().to_string()); }
+    });
+    assert_eq!(ours, vec!["first", "second"]);
+    assert!(total >= 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize() {
+    init();
+
+    let builder = thread::Builder::new().name("serialize-test".into());
+    let h = builder.spawn(|| { panic_monitor::set_context("job 1"); panic!("oops"); }).unwrap();
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let record = &PANIC_MONITOR.check_records([tid])[0];
+    let json = serde_json::to_value(record).unwrap();
+    assert_eq!(json["thread_number"], record.thread_number());
+    assert_eq!(json["thread_name"], "serialize-test");
+    assert_eq!(json["message"], "oops");
+    assert_eq!(json["location"]["file"], file!());
+    assert_eq!(json["context"], "job 1");
+    assert!(json["time"].is_object());
+}
+
+#[test]
+fn many_waiters() {
+    init();
+
+    // Each waiter watches its own worker; the workers panic one at a time, and each waiter should
+    // see only its own worker.
+    let workers: Vec<_> = (0..8u64).map(|i| thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20 * i));
+        panic!("worker {}", i);
+    })).collect();
+    let waiters: Vec<_> = workers.iter().map(|w| {
+        let tid = w.thread().id();
+        thread::spawn(move || {
+            let ts = PANIC_MONITOR.wait([tid]);
+            assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![tid]);
+        })
+    }).collect();
+    for w in waiters { w.join().unwrap(); }
+    for w in workers { w.join().unwrap_err(); }
+}
+
+#[test]
+fn cursor() {
+    init();
+
+    let old = thread::spawn(|| { panic!(); });
+    let old_id = old.thread().id();
+    old.join().unwrap_err();
+    let new = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let new_id = new.thread().id();
+
+    let mut cursor = PANIC_MONITOR.cursor([old_id, new_id]);
+    let ts: Vec<(ThreadId, bool)> = cursor.wait().into_iter().map(|(t, n)| (t.id(), n)).collect();
+    assert_eq!(ts, vec![(old_id, true)]);
+    // The old panic has been reported, so this time we block until the new one
+    let ts: Vec<(ThreadId, bool)> = cursor.wait().into_iter().map(|(t, n)| (t.id(), n)).collect();
+    assert_eq!(ts, vec![(old_id, false), (new_id, true)]);
+    assert!(cursor.wait_timeout(Duration::from_millis(10)).is_empty());
+    new.join().unwrap_err();
+}
+
+#[test]
+fn wait_new() {
+    init();
+
+    let old = thread::spawn(|| { panic!(); });
+    let old_id = old.thread().id();
+    old.join().unwrap_err();
+    let gen = PANIC_MONITOR.generation();
+    let new = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let new_id = new.thread().id();
+
+    let (ts, gen2) = PANIC_MONITOR.wait_new([old_id, new_id], gen);
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![new_id]);
+    assert!(gen2 > gen);
+    let (ts, _) = PANIC_MONITOR.wait_new([old_id, new_id], Default::default());
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![old_id, new_id]);
+    new.join().unwrap_err();
+}
+
+#[test]
+fn wait_filter() {
+    init();
+
+    let spawn = |name: &str, delay: u64| {
+        thread::Builder::new().name(name.to_string()).spawn(move || {
+            thread::sleep(Duration::from_millis(delay));
+            panic!();
+        }).unwrap()
+    };
+    let other = spawn("filter-other", 0);
+    let worker = spawn("filter-worker-1", 100);
+    other.join().unwrap_err();
+
+    let is_worker = |t: &thread::Thread| t.name().is_some_and(|n| n.starts_with("filter-worker-"));
+    let ts = PANIC_MONITOR.wait_filter(is_worker);
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![worker.thread().id()]);
+    worker.join().unwrap_err();
+}
+
+#[test]
+fn wait_named() {
+    init();
+
+    let spawn = |name: Option<&str>| {
+        let builder = match name {
+            Some(name) => thread::Builder::new().name(name.to_string()),
+            None => thread::Builder::new(),
+        };
+        builder.spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); }).unwrap()
+    };
+    let a1 = spawn(Some("named-a"));
+    let a2 = spawn(Some("named-a"));
+    let b = spawn(Some("named-b"));
+    let unnamed = spawn(None);
+    for h in [&a1, &a2, &b, &unnamed] { while !h.is_finished() { thread::yield_now(); } }
+
+    let ts: Vec<ThreadId> =
+        PANIC_MONITOR.wait_named(&["named-a", "<unnamed>"]).iter().map(|t| t.id()).collect();
+    assert_eq!(ts.len(), 2);
+    assert!(ts.contains(&a1.thread().id()) && ts.contains(&a2.thread().id()));
+    for h in [a1, a2, b, unnamed] { h.join().unwrap_err(); }
+}
+
+#[test]
+fn has_panicked() {
+    init();
+
+    let good = thread::spawn(|| {});
+    let bad = thread::spawn(|| { panic!(); });
+    let (good_id, bad_id) = (good.thread().id(), bad.thread().id());
+    good.join().unwrap();
+    bad.join().unwrap_err();
+
+    assert!(!PANIC_MONITOR.has_panicked(good_id));
+    assert!(PANIC_MONITOR.has_panicked(bad_id));
+    assert!(!PANIC_MONITOR.any_panicked([good_id]));
+    assert!(PANIC_MONITOR.any_panicked([good_id, bad_id]));
+    assert!(PANIC_MONITOR.any_panicked(ALL_THREADS));
+}
+
+#[test]
+fn wait_timeout_remaining() {
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let tid = h.thread().id();
+    let (ts, left) = PANIC_MONITOR.wait_timeout_remaining([tid], Duration::from_secs(10));
+    assert_eq!(ts.len(), 1);
+    assert!(left > Duration::from_secs(5) && left < Duration::from_secs(10));
+    h.join().unwrap_err();
+
+    let good = thread::spawn(|| {});
+    let good_id = good.thread().id();
+    let (ts, left) = PANIC_MONITOR.wait_timeout_remaining([good_id], Duration::from_millis(10));
+    assert!(ts.is_empty());
+    assert_eq!(left, Duration::from_secs(0));
+    good.join().unwrap();
+}
+
+#[test]
+fn wait_timeout_ms() {
+    init();
+
+    let good = thread::spawn(|| {});
+    let good_id = good.thread().id();
+    let start = Instant::now();
+    assert!(PANIC_MONITOR.wait_timeout_ms([good_id], 20).is_empty());
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    good.join().unwrap();
+
+    let h = thread::spawn(|| { panic!("ms"); });
+    let tid = h.thread().id();
+    assert_eq!(PANIC_MONITOR.wait_timeout_ms([tid], 10_000)[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.wait_timeout_records_ms([tid], 0)[0].message(), "ms");
+    h.join().unwrap_err();
+}
+
+#[test]
+fn wait_cancellable() {
+    init();
+
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); });
+    let good_id = good.thread().id();
+    let token = CancelToken::new();
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || { thread::sleep(Duration::from_millis(50)); token.cancel(); })
+    };
+    assert!(PANIC_MONITOR.wait_cancellable([good_id], &token).is_none());
+    assert!(token.is_cancelled());
+    canceller.join().unwrap();
+    good.join().unwrap();
+
+    // A panic which has already happened is still reported, even if the token is cancelled
+    let bad = thread::spawn(|| { panic!(); });
+    let bad_id = bad.thread().id();
+    bad.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.wait_cancellable([bad_id], &token).unwrap().len(), 1);
+}
+
+#[test]
+fn ordering() {
+    init();
+
+    let a = thread::spawn(|| { panic!(); });
+    let b = thread::spawn(|| { panic!(); });
+    let (a_id, b_id) = (a.thread().id(), b.thread().id());
+    a.join().unwrap_err();
+    b.join().unwrap_err();
+
+    let ids = |ts: Vec<thread::Thread>| ts.iter().map(|t| t.id()).collect::<Vec<_>>();
+    assert_eq!(ids(PANIC_MONITOR.check([b_id, a_id, b_id])), vec![b_id, a_id]);
+    assert_eq!(ids(PANIC_MONITOR.wait([a_id, a_id, b_id])), vec![a_id, b_id]);
+}
+
+#[test]
+fn on_panic() {
+    use std::sync::{Arc, Mutex};
+    init();
+
+    let name = "on-panic-callback";
+    let seen = Arc::new(Mutex::new(vec![]));
+    for i in 0..2 {
+        let seen = seen.clone();
+        PANIC_MONITOR.on_panic(move |t| {
+            if t.name() == Some(name) { seen.lock().unwrap().push(i); }
+        });
+    }
+    let h = thread::Builder::new().name(name.to_string()).spawn(|| { panic!(); }).unwrap();
+    let tid = h.thread().id();
+    PANIC_MONITOR.wait([tid]);
+    // The callbacks have already run by the time `wait` returns
+    assert_eq!(*seen.lock().unwrap(), vec![0, 1]);
+    h.join().unwrap_err();
+}
+
+#[test]
+fn wait_group() {
+    init();
+
+    let workers = ThreadGroup::new("workers");
+    assert!(PANIC_MONITOR.wait_group(&workers).is_empty());
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); });
+    let bad = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let (good_id, bad_id) = (good.thread().id(), bad.thread().id());
+    assert!(workers.add(good_id));
+    assert!(workers.clone().add(bad_id));
+    assert!(!workers.add(bad_id));
+    assert_eq!(workers.ids(), vec![good_id, bad_id]);
+
+    let ts = PANIC_MONITOR.wait_group(&workers);
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![bad_id]);
+    assert!(workers.remove(bad_id));
+    assert!(!workers.contains(bad_id));
+    assert_eq!(workers.len(), 1);
+    good.join().unwrap();
+    bad.join().unwrap_err();
+}
+
+#[test]
+fn wait_handle() {
+    init();
+
+    let handle = WatchHandle::new();
+    let waiter = {
+        let handle = handle.clone();
+        thread::spawn(move || PANIC_MONITOR.wait_handle(&handle))
+    };
+    // Added after the waiter has started waiting
+    thread::sleep(Duration::from_millis(50));
+    let early = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let late = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); panic!(); });
+    let (early_id, late_id) = (early.thread().id(), late.thread().id());
+    assert!(handle.add(early_id));
+    assert!(handle.add(late_id));
+    assert!(handle.remove(early_id));
+    assert!(!handle.remove(early_id));
+    assert_eq!(handle.ids(), vec![late_id]);
+
+    let ts = waiter.join().unwrap();
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![late_id]);
+    early.join().unwrap_err();
+    late.join().unwrap_err();
+}
+
+#[test]
+fn wait_dynamic() {
+    use std::sync::Mutex;
+
+    init();
+
+    let workers: &'static Mutex<Vec<ThreadId>> = Box::leak(Box::new(Mutex::new(vec![])));
+    let supervisor = thread::spawn(move || {
+        PANIC_MONITOR.wait_dynamic(|| workers.lock().unwrap().clone())[0].id()
+    });
+    thread::sleep(Duration::from_millis(50));
+
+    // A worker which joins the list while the supervisor is blocked gets noticed
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let h = thread::spawn(move || { let _ = rx.recv(); panic!(); });
+    let tid = h.thread().id();
+    workers.lock().unwrap().push(tid);
+    drop(tx);
+    assert_eq!(supervisor.join().unwrap(), tid);
+    h.join().unwrap_err();
+}
+
+#[test]
+fn watch_list_types() {
+    use std::collections::HashSet;
+
+    init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    let other = thread::current().id();
+
+    let set: HashSet<ThreadId> = vec![tid, other].into_iter().collect();
+    assert_eq!(PANIC_MONITOR.check(&set).len(), 1);
+    assert_eq!(PANIC_MONITOR.check([tid, other]).len(), 1);
+    assert_eq!(PANIC_MONITOR.check(vec![other, tid]).len(), 1);
+    assert_eq!(PANIC_MONITOR.check(set.iter().filter(|&&t| t != tid)).len(), 0);
+    assert_eq!(PANIC_MONITOR.wait(Some(tid))[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.wait([other, tid])[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.wait(vec![tid, other].into_iter())[0].id(), tid);
+}
+
+#[test]
+fn watchable() {
+    init();
+
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); });
+    let bad = thread::spawn(|| { panic!(); });
+    let tid = bad.thread().id();
+    assert_eq!(PANIC_MONITOR.wait([&good, &bad])[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.wait(vec![&bad])[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.check([good.thread(), bad.thread()])[0].id(), tid);
+    assert_eq!(PANIC_MONITOR.check(vec![bad.thread().clone()])[0].id(), tid);
+
+    // The handles are only borrowed
+    good.join().unwrap();
+    bad.join().unwrap_err();
+}
+
+#[test]
+fn wait_first() {
+    init();
+
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); });
+    let a = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!(); });
+    let b = thread::spawn(|| { panic!(); });
+    let (good_id, a_id, b_id) = (good.thread().id(), a.thread().id(), b.thread().id());
+    a.join().unwrap_err();
+    b.join().unwrap_err();
+    // Watch-list order, not the order they panicked in
+    assert_eq!(PANIC_MONITOR.wait_first([good_id, a_id, b_id]).id(), a_id);
+    assert_eq!(PANIC_MONITOR.wait_first([b_id, a_id]).id(), b_id);
+    good.join().unwrap();
+}
+
+#[test]
+fn check_into() {
+    init();
+
+    let h = thread::spawn(|| { panic!(); });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let mut buf = Vec::with_capacity(4);
+    PANIC_MONITOR.check_into(&[tid, tid], &mut buf);
+    assert_eq!(buf.len(), 1);
+    assert_eq!(buf[0].id(), tid);
+    let good = thread::spawn(|| {});
+    PANIC_MONITOR.check_into(&[good.thread().id()], &mut buf);
+    assert!(buf.is_empty());
+    assert!(buf.capacity() >= 4);
+    good.join().unwrap();
+}
+
+#[test]
+fn check_ids_and_names() {
+    init();
+
+    let named = thread::Builder::new().name("check-names".into()).spawn(|| { panic!(); }).unwrap();
+    let unnamed = thread::spawn(|| { panic!(); });
+    let good = thread::spawn(|| {});
+    let tids = [named.thread().id(), good.thread().id(), unnamed.thread().id()];
+    named.join().unwrap_err();
+    unnamed.join().unwrap_err();
+    good.join().unwrap();
+
+    assert_eq!(PANIC_MONITOR.check_ids(tids), [tids[0], tids[2]]);
+    assert_eq!(PANIC_MONITOR.check_names(tids), [Some("check-names".to_owned()), None]);
+}
+
+#[test]
+fn wait_any() {
+    init();
+
+    let other = PanicMonitor::new_arc();
+    other.init_arc();
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(200)); });
+    let bad = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let (good_id, bad_id) = (good.thread().id(), bad.thread().id());
+
+    let (i, ts) = panic_monitor::wait_any(&[(&PANIC_MONITOR, &[good_id]), (&other, &[bad_id])]);
+    assert_eq!(i, 1);
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![bad_id]);
+    bad.join().unwrap_err();
+    // Both monitors have seen the panic, so now the first one wins
+    let (i, _) = panic_monitor::wait_any(&[(&PANIC_MONITOR, &[bad_id]), (&other, &[bad_id])]);
+    assert_eq!(i, 0);
+    good.join().unwrap();
+}
+
+#[test]
+fn context() {
+    init();
+
+    let h = thread::spawn(|| {
+        panic_monitor::set_context("shard 6");
+        panic_monitor::set_context(format!("shard {}", 7));
+        panic!();
+    });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.check_records([tid])[0].context(), Some("shard 7"));
+
+    let h = thread::spawn(|| {
+        panic_monitor::set_context("shard 8");
+        panic_monitor::clear_context();
+        panic!();
+    });
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.check_records([tid])[0].context(), None);
+}
+
+#[test]
+fn send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PanicMonitor>();
+    assert_send_sync::<panic_monitor::PanicRecord>();
+    assert_send_sync::<CancelToken>();
+    assert_send_sync::<ThreadGroup>();
+}
+
+#[test]
+fn catch_unwind() {
+    init();
+
+    let h = thread::spawn(|| {
+        assert!(panic_monitor::catch_unwind(|| panic!("recovered")).is_err());
+        assert_eq!(panic_monitor::catch_unwind(|| 5).unwrap(), 5);
+        thread::sleep(Duration::from_millis(50));
+        panic!("died");
+    });
+    let tid = h.thread().id();
+    PANIC_MONITOR.wait([tid]);
+    let history = PANIC_MONITOR.history(tid);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].message(), "died");
+    h.join().unwrap_err();
+}
+
+#[test]
+fn abort_after_zero() {
+    init();
+
+    let h = thread::spawn(|| PanicMonitor::builder().abort_after(0, Duration::from_secs(1)));
+    let payload = h.join().unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"abort_after: count must be at least 1"));
+}
+
+#[test]
+fn max_history_zero() {
+    init();
+
+    let payload = thread::spawn(|| PanicMonitor::builder().max_history(0)).join().unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"max_history: max must be at least 1"));
+}
+
+#[test]
+fn with_capacity_zero() {
+    init();
+
+    let h = thread::spawn(|| { PanicMonitor::with_capacity(0); });
+    let payload = h.join().unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"with_capacity: max must be at least 1"));
+}
+
+#[test]
+fn wait_count() {
+    init();
+
+    let hs: Vec<_> = (0..3u64).map(|i| thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50 * (i + 1)));
+        if i < 2 { panic!(); }
+    })).collect();
+    let ids: Vec<ThreadId> = hs.iter().map(|h| h.thread().id()).collect();
+
+    assert!(PANIC_MONITOR.wait_count(&ids, 0).is_empty());
+    let ts = PANIC_MONITOR.wait_count([ids[0], ids[1], ids[2], ids[0]], 2);
+    assert_eq!(ts.iter().map(|t| t.id()).collect::<Vec<_>>(), vec![ids[0], ids[1]]);
+    let too_many = std::panic::catch_unwind(|| PANIC_MONITOR.wait_count(&ids[..2], 3));
+    assert!(too_many.is_err());
+    for (i, h) in hs.into_iter().enumerate() { assert_eq!(h.join().is_err(), i < 2); }
+}
+
+#[test]
+#[cfg(feature = "crossbeam")]
+fn subscribe_crossbeam() {
+    init();
+
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let tid = h.thread().id();
+    let panics = PANIC_MONITOR.subscribe_crossbeam([tid]);
+    let (_tx, other) = crossbeam_channel::unbounded::<()>();
+    crossbeam_channel::select! {
+        recv(panics) -> t => assert_eq!(t.unwrap().id(), tid),
+        recv(other) -> _ => unreachable!(),
+    }
+    h.join().unwrap_err();
+}
+
+#[test]
+fn join_all() {
+    init();
+
+    let stop = CancelToken::new();
+    let token = stop.clone();
+    let worker = PANIC_MONITOR.spawn_owned("join-all-worker", move || {
+        while !token.is_cancelled() { thread::sleep(Duration::from_millis(1)); }
+    }).unwrap();
+    let broken = PANIC_MONITOR.spawn_owned("join-all-broken", || panic!()).unwrap();
+    assert!(PANIC_MONITOR.spawned().contains(&worker));
+
+    stop.cancel();
+    let outcomes = PANIC_MONITOR.join_all();
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0].0, worker);
+    assert!(matches!(outcomes[0].1, Outcome::Finished));
+    assert_eq!(outcomes[1].0, broken);
+    assert!(matches!(outcomes[1].1, Outcome::Panicked(ref t) if t.id() == broken));
+
+    // The handles have been used up
+    assert!(PANIC_MONITOR.join_all().is_empty());
+}
+
+#[test]
+fn classify_payloads() {
+    #[derive(Debug)]
+    enum Crash { Timeout, Corrupt }
+
+    init();
+    PANIC_MONITOR.classify_payloads(|payload| match payload.downcast_ref::<Crash>() {
+        Some(Crash::Timeout) => Some(1),
+        Some(Crash::Corrupt) => Some(2),
+        None => None,
+    });
+
+    let timeout = thread::spawn(|| { std::panic::panic_any(Crash::Timeout); });
+    let corrupt = thread::spawn(|| { std::panic::panic_any(Crash::Corrupt); });
+    let plain = thread::spawn(|| { panic!("plain"); });
+    assert_eq!(PANIC_MONITOR.wait_records([&timeout])[0].class(), Some(1));
+    assert_eq!(PANIC_MONITOR.wait_records([&corrupt])[0].class(), Some(2));
+    assert_eq!(PANIC_MONITOR.wait_records([&plain])[0].class(), None);
+    timeout.join().unwrap_err();
+    corrupt.join().unwrap_err();
+    plain.join().unwrap_err();
+}
+
+#[test]
+fn count_by_name() {
+    init();
+
+    let pool: Vec<_> = (0..3).map(|_| {
+        thread::Builder::new().name("count-by-name-pool".into()).spawn(|| { panic!(); }).unwrap()
+    }).collect();
+    let io = thread::Builder::new().name("count-by-name-io".into()).spawn(|| {
+        let _ = std::panic::catch_unwind(|| panic!());
+        panic!();
+    }).unwrap();
+    let unnamed = thread::spawn(|| { panic!(); });
+    for h in pool { h.join().unwrap_err(); }
+    io.join().unwrap_err();
+    unnamed.join().unwrap_err();
+
+    let counts = PANIC_MONITOR.count_by_name();
+    assert_eq!(counts[&Some("count-by-name-pool".to_string())], 3);
+    assert_eq!(counts[&Some("count-by-name-io".to_string())], 2);
+    assert!(counts[&None] >= 1);
+}
+
+#[test]
+fn wait_fixed() {
+    init();
+
+    let good = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); });
+    let bad = thread::spawn(|| { thread::sleep(Duration::from_millis(20)); panic!(); });
+    let (good_id, bad_id) = (good.thread().id(), bad.thread().id());
+    let [a, b, c] = PANIC_MONITOR.wait_fixed(&[good_id, bad_id, bad_id]);
+    assert!(a.is_none());
+    assert_eq!(b.unwrap().id(), bad_id);
+    assert_eq!(c.unwrap().id(), bad_id);
+    good.join().unwrap();
+    bad.join().unwrap_err();
+}
+
+#[test]
+fn record_as_error() {
+    use std::error::Error;
+
+    init();
+
+    let h = thread::Builder::new().name("error-worker".into())
+        .spawn(|| { panic!("out of cheese"); }).unwrap();
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+    let supervise = || -> Result<(), Box<dyn Error + Send + Sync>> {
+        match PANIC_MONITOR.check_records([tid]).pop() {
+            Some(record) => Err(record.into()),
+            None => Ok(()),
+        }
+    };
+    let err = supervise().unwrap_err().to_string();
+    let number = PANIC_MONITOR.thread_number(tid).unwrap();
+    let prefix = format!("thread 'error-worker' (#{}) panicked at {}:", number, file!());
+    assert!(err.starts_with(&prefix) && err.ends_with(": out of cheese"), "{}", err);
+}
+
+#[test]
+fn wait_and_propagate() {
+    init();
+
+    let worker = thread::Builder::new().name("propagate-worker".into())
+        .spawn(|| { panic!("disk full"); }).unwrap();
+    let tid = worker.thread().id();
+    let supervisor = thread::spawn(move || PANIC_MONITOR.wait_and_propagate([tid]));
+    let payload = supervisor.join().unwrap_err();
+    let msg = payload.downcast_ref::<String>().unwrap();
+    let number = PANIC_MONITOR.thread_number(tid).unwrap();
+    assert!(msg.starts_with(&format!("thread 'propagate-worker' (#{}) panicked at ", number)));
+    assert!(msg.contains(file!()) && msg.ends_with(": disk full"), "{}", msg);
+    worker.join().unwrap_err();
+}
+
+#[test]
+fn thread_number() {
+    init();
+
+    let (h, spawned) = PANIC_MONITOR.spawn("thread-number", || {
+        thread::sleep(Duration::from_millis(50));
+        panic!();
+    }).unwrap();
+    // Threads spawned by the monitor are numbered straight away
+    let number = PANIC_MONITOR.thread_number(spawned).unwrap();
+    let unseen = thread::spawn(|| { thread::sleep(Duration::from_millis(50)); panic!(); });
+    let unseen_id = unseen.thread().id();
+    assert_eq!(PANIC_MONITOR.thread_number(unseen_id), None);
+
+    h.join().unwrap_err();
+    unseen.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.history(spawned)[0].thread_number(), number);
+    let unseen_number = PANIC_MONITOR.thread_number(unseen_id).unwrap();
+    assert_ne!(unseen_number, number);
+    assert_eq!(PANIC_MONITOR.history(unseen_id)[0].thread_number(), unseen_number);
+}
+
+#[test]
+fn register_thread() {
+    init();
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let h = thread::spawn(move || { let _ = rx.recv(); panic!(); });
+    let tid = h.thread().id();
+    assert_eq!(PANIC_MONITOR.thread_number(tid), None);
+    let number = PANIC_MONITOR.register_thread(h.thread());
+    assert_eq!(PANIC_MONITOR.thread_number(tid), Some(number));
+    assert_eq!(PANIC_MONITOR.register_thread(h.thread()), number);
+
+    // The number sticks once the thread panics
+    drop(tx);
+    h.join().unwrap_err();
+    assert_eq!(PANIC_MONITOR.wait_records([tid])[0].thread_number(), number);
+}
+
+#[test]
+fn wait_done() {
+    init();
+
+    let (h1, finishes) = PANIC_MONITOR.spawn("wait-done-ok", || {
+        thread::sleep(Duration::from_millis(50));
+        7
+    }).unwrap();
+    let (h2, panics) = PANIC_MONITOR.spawn("wait-done-panic", || {
+        thread::sleep(Duration::from_millis(50));
+        panic!();
+    }).unwrap();
+    match PANIC_MONITOR.wait_done(finishes) {
+        Outcome::Finished => {}
+        Outcome::Panicked(_) => panic!("thread should have finished"),
+    }
+    match PANIC_MONITOR.wait_done(panics) {
+        Outcome::Panicked(t) => assert_eq!(t.id(), panics),
+        Outcome::Finished => panic!("thread should have panicked"),
+    }
+    // Still answers once the threads are long gone
+    assert_eq!(h1.join().unwrap(), 7);
+    h2.join().unwrap_err();
+    match PANIC_MONITOR.wait_done(finishes) {
+        Outcome::Finished => {}
+        Outcome::Panicked(_) => panic!("thread should have finished"),
+    }
+}
+
+#[test]
+fn wait_for_record() {
+    init();
+
+    let h = thread::spawn(|| {
+        let _ = std::panic::catch_unwind(|| panic!("recorded"));
+        // Our own panic was recorded before we started unwinding
+        PANIC_MONITOR.wait_for_record(thread::current().id()).message().to_owned()
+    });
+    let tid = h.thread().id();
+    assert_eq!(h.join().unwrap(), "recorded");
+    let record = PANIC_MONITOR.wait_for_record(tid);
+    assert_eq!(record.thread().id(), tid);
+    assert_eq!(record.message(), "recorded");
+}
@@ -0,0 +1,35 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+use std::collections::HashSet;
+use std::thread::ThreadId;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+fn panic_threads(n: usize) -> Vec<ThreadId> {
+    (0..n).map(|_| panic_thread()).collect()
+}
+
+#[test]
+fn thread_ids_are_not_reused() {
+    PANIC_MONITOR.init();
+
+    // Each thread is joined before the next is spawned, so an ID could be recycled if it were ever
+    // going to be
+    let first = panic_threads(200);
+    let distinct: HashSet<_> = first.iter().cloned().collect();
+    assert_eq!(distinct.len(), 200);
+    assert_eq!(PANIC_MONITOR.len(), 200);
+    assert!(first.iter().all(|&tid| PANIC_MONITOR.history(tid).len() == 1));
+
+    // Forgetting the old threads doesn't free their IDs up for new ones
+    for &tid in &first { PANIC_MONITOR.forget(tid); }
+    assert!(PANIC_MONITOR.is_empty());
+    let second = panic_threads(200);
+    assert!(second.iter().all(|tid| !distinct.contains(tid)));
+    assert_eq!(PANIC_MONITOR.len(), 200);
+    assert!(PANIC_MONITOR.check(&first).is_empty());
+}
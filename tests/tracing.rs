@@ -0,0 +1,66 @@
+#![cfg(feature = "tracing")]
+
+extern crate panic_monitor;
+extern crate tracing;
+
+use panic_monitor::PanicMonitor;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new().trace_panics(true);
+
+static EVENTS: Mutex<Vec<(Level, HashMap<String, String>)>> = Mutex::new(Vec::new());
+
+struct TestSubscriber;
+
+struct Fields(HashMap<String, String>);
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for TestSubscriber {
+    fn enabled(&self, _: &Metadata) -> bool { true }
+    fn new_span(&self, _: &Attributes) -> Id { Id::from_u64(1) }
+    fn record(&self, _: &Id, _: &Record) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, event: &Event) {
+        let mut fields = Fields(HashMap::new());
+        event.record(&mut fields);
+        EVENTS.lock().unwrap().push((*event.metadata().level(), fields.0));
+    }
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn trace_panics() {
+    tracing::subscriber::set_global_default(TestSubscriber).unwrap();
+    PANIC_MONITOR.init();
+
+    let builder = thread::Builder::new().name("tracing-test".into());
+    let h = builder.spawn(|| { panic!("oops"); }).unwrap();
+    let tid = h.thread().id();
+    h.join().unwrap_err();
+
+    let events = EVENTS.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    let (level, ref fields) = events[0];
+    assert_eq!(level, Level::ERROR);
+    assert_eq!(fields["thread.name"], "tracing-test");
+    let number = PANIC_MONITOR.check_records([tid])[0].thread_number();
+    assert_eq!(fields["thread.id"], number.to_string());
+    assert_eq!(fields["panic.message"], "oops");
+    assert!(fields["panic.location"].contains(file!()));
+    assert_eq!(fields["panic.double"], "false");
+}
@@ -0,0 +1,45 @@
+extern crate panic_monitor;
+
+mod common;
+
+use common::panic_thread;
+use panic_monitor::PanicMonitor;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+static ORIGINAL_HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn uninstall() {
+    panic::set_hook(Box::new(|_| { ORIGINAL_HOOK_CALLS.fetch_add(1, Ordering::SeqCst); }));
+    assert!(!PANIC_MONITOR.is_initialized());
+    PANIC_MONITOR.init();
+    assert!(PANIC_MONITOR.is_initialized());
+    let recorded = panic_thread();
+    assert!(PANIC_MONITOR.has_panicked(recorded));
+    assert_eq!(ORIGINAL_HOOK_CALLS.load(Ordering::SeqCst), 1);
+
+    // Once uninstalled, panics go straight to the original hook, but old records are kept
+    PANIC_MONITOR.uninstall();
+    assert!(!PANIC_MONITOR.is_initialized());
+    let tid = panic_thread();
+    assert!(!PANIC_MONITOR.has_panicked(tid));
+    assert!(PANIC_MONITOR.has_panicked(recorded));
+    assert_eq!(ORIGINAL_HOOK_CALLS.load(Ordering::SeqCst), 2);
+
+    // A hook which wrapped ours doesn't bring it back to life
+    PANIC_MONITOR.init();
+    let wrapped = panic::take_hook();
+    panic::set_hook(Box::new(move |x| wrapped(x)));
+    PANIC_MONITOR.uninstall();
+    let tid = panic_thread();
+    assert!(!PANIC_MONITOR.has_panicked(tid));
+    assert_eq!(ORIGINAL_HOOK_CALLS.load(Ordering::SeqCst), 3);
+
+    // It can be initialised again afterwards
+    PANIC_MONITOR.init();
+    let tid = panic_thread();
+    assert!(PANIC_MONITOR.has_panicked(tid));
+    assert_eq!(ORIGINAL_HOOK_CALLS.load(Ordering::SeqCst), 4);
+}
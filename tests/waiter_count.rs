@@ -0,0 +1,37 @@
+extern crate panic_monitor;
+
+use panic_monitor::PanicMonitor;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// Other tests' waiters would affect the count.
+#[test]
+fn waiter_count() {
+    PANIC_MONITOR.init();
+    assert_eq!(PANIC_MONITOR.waiter_count(), 0);
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let worker = thread::spawn(move || { let _ = rx.recv(); panic!(); });
+    let tid = worker.thread().id();
+    let waiters: Vec<_> = (0..4)
+        .map(|_| thread::spawn(move || { PANIC_MONITOR.wait([tid]); }))
+        .collect();
+    while PANIC_MONITOR.waiter_count() < 4 { thread::sleep(Duration::from_millis(1)); }
+    assert_eq!(PANIC_MONITOR.waiter_count(), 4);
+
+    // A timed-out waiter stops being counted
+    PANIC_MONITOR.wait_timeout([tid], Duration::from_millis(10));
+    assert_eq!(PANIC_MONITOR.waiter_count(), 4);
+
+    drop(tx);
+    worker.join().unwrap_err();
+    for h in waiters { h.join().unwrap(); }
+    assert_eq!(PANIC_MONITOR.waiter_count(), 0);
+
+    // Returning straight away doesn't count
+    PANIC_MONITOR.wait([tid]);
+    assert_eq!(PANIC_MONITOR.waiter_count(), 0);
+}
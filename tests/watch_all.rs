@@ -0,0 +1,44 @@
+extern crate panic_monitor;
+
+use panic_monitor::{PanicMonitor, ALL_THREADS};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+static PANIC_MONITOR: PanicMonitor = PanicMonitor::new();
+
+// Other tests' panics would wake the watcher.
+#[test]
+fn empty_watch_list() {
+    PANIC_MONITOR.init();
+
+    assert!(PANIC_MONITOR.check(ALL_THREADS).is_empty());
+    assert!(PANIC_MONITOR.wait_timeout(ALL_THREADS, Duration::from_millis(10)).is_empty());
+
+    // The whole timeout is only used up if no thread panics
+    let h = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let start = Instant::now();
+    let t = PANIC_MONITOR.wait_timeout(ALL_THREADS, Duration::from_secs(10));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(t.iter().map(|x| x.id()).collect::<Vec<_>>(), vec![h.thread().id()]);
+    h.join().unwrap_err();
+    PANIC_MONITOR.clear();
+
+    let a = thread::spawn(|| { thread::sleep(Duration::from_millis(100)); panic!(); });
+    let a_id = a.thread().id();
+    let t = PANIC_MONITOR.wait(ALL_THREADS);
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![a_id]);
+    a.join().unwrap_err();
+
+    let b = thread::spawn(|| { panic!(); });
+    let b_id = b.thread().id();
+    b.join().unwrap_err();
+    let t = PANIC_MONITOR.check(ALL_THREADS);
+    let t: Vec<ThreadId> = t.iter().map(|x|x.id()).collect();
+    assert_eq!(t, vec![a_id, b_id]);
+
+    // The timeout isn't wasted when some thread has already panicked
+    let start = Instant::now();
+    assert!(!PANIC_MONITOR.wait_timeout(ALL_THREADS, Duration::from_secs(10)).is_empty());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}